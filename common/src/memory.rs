@@ -0,0 +1,43 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+
+// The resident memory of the whole process in KB, 0 where it is not known.
+// It includes the buffers of the sessions but also everything else.
+pub fn resident_memory() -> usize {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| vm_rss(&status))
+        .unwrap_or(0)
+}
+
+// The VmRSS line of /proc/<pid>/status, in KB
+fn vm_rss(status: &str) -> Option<usize> {
+    status
+        .lines()
+        .find(|l| l.starts_with("VmRSS:"))
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|v| v.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vm_rss_of_a_status() {
+        let status = "Name:\tzn_router\nVmPeak:\t  20000 kB\nVmRSS:\t   1234 kB\nThreads:\t4\n";
+        assert_eq!(vm_rss(status), Some(1234));
+        assert_eq!(vm_rss("Name:\tzn_router\n"), None);
+    }
+}
@@ -0,0 +1,67 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use zenoh::net::protocol::core::{CongestionControl, Priority, Reliability};
+use zenoh::net::protocol::io::{WBuf, ZBuf};
+
+// The count marking the background messages echoed back by the pong
+pub const BACKGROUND: u64 = u64::MAX;
+
+pub fn reliability(reliability: &str) -> Reliability {
+    match reliability {
+        "reliable" => Reliability::Reliable,
+        "best-effort" => Reliability::BestEffort,
+        _ => panic!("Unsupported reliability: {}", reliability),
+    }
+}
+
+pub fn congestion_control(congestion: &str) -> CongestionControl {
+    match congestion {
+        "block" => CongestionControl::Block,
+        "drop" => CongestionControl::Drop,
+        _ => panic!("Unsupported congestion control: {}", congestion),
+    }
+}
+
+pub fn priority(priority: &str) -> Priority {
+    match priority {
+        "real-time" => Priority::RealTime,
+        "interactive-high" => Priority::InteractiveHigh,
+        "interactive-low" => Priority::InteractiveLow,
+        "data-high" => Priority::DataHigh,
+        "data" => Priority::Data,
+        "data-low" => Priority::DataLow,
+        "background" => Priority::Background,
+        _ => panic!("Unsupported priority: {}", priority),
+    }
+}
+
+// The count is written on the first 8 bytes of the ping payloads
+pub fn count_payload(count: u64, payload: usize) -> ZBuf {
+    let mut data: WBuf = WBuf::new(payload, true);
+    data.write_bytes(&count.to_le_bytes());
+    data.write_bytes(&vec![0u8; payload - 8]);
+    data.into()
+}
+
+pub fn payload_count(payload: &mut ZBuf) -> u64 {
+    let mut count_bytes = [0u8; 8];
+    payload.read_bytes(&mut count_bytes);
+    u64::from_le_bytes(count_bytes)
+}
+
+// Messages can be lost either over a best-effort subscription or when the
+// publisher is allowed to drop them on congestion
+pub fn lossy(reliability: Reliability, congestion_control: CongestionControl) -> bool {
+    reliability == Reliability::BestEffort || congestion_control == CongestionControl::Drop
+}
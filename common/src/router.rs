@@ -0,0 +1,63 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use zenoh::Properties;
+use zenoh_util::properties::config::{ConfigProperties, ZN_LISTENER_KEY};
+
+// A router listens on the default port unless told otherwise, which may be the
+// one of the peer it connects to on the same host: the routers that connect
+// rather than listen get a local port of their own instead
+const LOCAL_LISTENER: &str = "tcp/127.0.0.1:0";
+
+// Give a listener to a router of the zenoh and zenoh-net layers without one
+pub fn configure(mode: &str, config: &mut Properties) {
+    if mode == "router" && !config.contains_key("listener") {
+        config.insert("listener".to_string(), LOCAL_LISTENER.to_string());
+    }
+}
+
+// Give a listener to a router of the runtime layer without one
+pub fn configure_runtime(mode: &str, config: &mut ConfigProperties) {
+    if mode == "router" && !config.contains_key(&ZN_LISTENER_KEY) {
+        config.insert(ZN_LISTENER_KEY, LOCAL_LISTENER.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn router_without_listener() {
+        let mut config = Properties::default();
+        configure("router", &mut config);
+        assert_eq!(config.get("listener").unwrap(), LOCAL_LISTENER);
+    }
+
+    #[test]
+    fn router_with_listener() {
+        let mut config = Properties::default();
+        config.insert("listener".to_string(), "tcp/127.0.0.1:7448".to_string());
+        configure("router", &mut config);
+        assert_eq!(config.get("listener").unwrap(), "tcp/127.0.0.1:7448");
+    }
+
+    #[test]
+    fn peer_and_client() {
+        for mode in &["peer", "client"] {
+            let mut config = ConfigProperties::default();
+            configure_runtime(mode, &mut config);
+            assert!(!config.contains_key(&ZN_LISTENER_KEY));
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use zenoh::net::protocol::core::Reliability;
+use zenoh::net::protocol::io::{WBuf, ZBuf};
+
+// The size of the sequence number stamped on the first bytes of the payloads
+pub const SEQ_SIZE: usize = 8;
+
+// Set on the stamped sequence numbers: the payloads of a publisher that does
+// not stamp them start with small bytes, so they are not mistaken for ones
+const STAMPED: u64 = 1 << 63;
+
+// The payloads need room for the sequence number
+pub fn check_payload(payload: usize) {
+    if payload < SEQ_SIZE {
+        panic!("Unsupported payload: {}", payload);
+    }
+}
+
+// The best-effort payloads are the ones stamped with their sequence number
+pub fn check_best_effort_payload(reliability: Reliability, payload: usize) {
+    if reliability == Reliability::BestEffort {
+        check_payload(payload);
+    }
+}
+
+pub fn header(seq: u64) -> [u8; SEQ_SIZE] {
+    (seq | STAMPED).to_le_bytes()
+}
+
+// Stamp the sequence number on the first bytes of the payload so that the
+// best-effort subscribers can account for the lost messages
+pub fn stamp(payload: &[u8], seq: u64) -> ZBuf {
+    let mut data = WBuf::new(payload.len(), true);
+    data.write_bytes(&header(seq));
+    data.write_bytes(&payload[SEQ_SIZE..]);
+    data.into()
+}
+
+// The sequence number stamped on a payload, if it was stamped
+pub fn unstamp(header: [u8; SEQ_SIZE]) -> Option<u64> {
+    let seq = u64::from_le_bytes(header);
+    if seq & STAMPED != 0 {
+        Some(seq & !STAMPED)
+    } else {
+        None
+    }
+}
+
+// Account for the messages lost in between the expected sequence number and
+// the received one
+pub fn account(next: &AtomicU64, lost: &AtomicUsize, seq: u64) {
+    let expected = next.fetch_max(seq + 1, Ordering::Relaxed);
+    if seq > expected {
+        lost.fetch_add((seq - expected) as usize, Ordering::Relaxed);
+    }
+}
+
+// Account for the sequence number stamped on a payload, the payloads that are
+// too short or not stamped are ignored
+pub fn account_payload(next: &AtomicU64, lost: &AtomicUsize, payload: &mut ZBuf) {
+    let mut header = [0u8; SEQ_SIZE];
+    if payload.read_bytes(&mut header) {
+        if let Some(seq) = unstamp(header) {
+            account(next, lost, seq);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lost(seqs: &[u64]) -> usize {
+        let next = AtomicU64::new(0);
+        let lost = AtomicUsize::new(0);
+        for seq in seqs {
+            account(&next, &lost, *seq);
+        }
+        lost.load(Ordering::Relaxed)
+    }
+
+    #[test]
+    fn account_in_order() {
+        assert_eq!(lost(&[0, 1, 2, 3]), 0);
+    }
+
+    #[test]
+    fn account_gaps() {
+        assert_eq!(lost(&[0, 3, 4, 10]), 7);
+        // The first messages are lost too
+        assert_eq!(lost(&[2, 3]), 2);
+    }
+
+    #[test]
+    fn account_late_and_duplicate() {
+        // A late message has already been accounted as lost
+        assert_eq!(lost(&[0, 2, 1, 3]), 1);
+        assert_eq!(lost(&[0, 1, 1, 2]), 0);
+    }
+
+    #[test]
+    fn unstamp_stamped_headers() {
+        for seq in [0, 1, 42, u64::MAX >> 1].iter() {
+            assert_eq!(unstamp(header(*seq)), Some(*seq));
+        }
+    }
+
+    #[test]
+    fn unstamp_payloads_not_stamped() {
+        assert_eq!(unstamp([0u8; SEQ_SIZE]), None);
+        assert_eq!(unstamp([0, 1, 2, 3, 4, 5, 6, 7]), None);
+    }
+
+    #[test]
+    fn account_stamped_payloads() {
+        let next = AtomicU64::new(0);
+        let lost = AtomicUsize::new(0);
+        let bytes = [0u8; 16];
+        for seq in [0, 3].iter() {
+            account_payload(&next, &lost, &mut stamp(&bytes, *seq));
+        }
+        assert_eq!(lost.load(Ordering::Relaxed), 2);
+
+        // Neither the payloads that are not stamped nor the short ones move
+        // the expected sequence number
+        let mut data = WBuf::new(bytes.len(), true);
+        data.write_bytes(&bytes);
+        account_payload(&next, &lost, &mut data.into());
+        let mut data = WBuf::new(4, true);
+        data.write_bytes(&bytes[..4]);
+        account_payload(&next, &lost, &mut data.into());
+        assert_eq!(next.load(Ordering::Relaxed), 4);
+        assert_eq!(lost.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported payload: 7")]
+    fn check_small_payloads() {
+        check_payload(7);
+    }
+}
@@ -0,0 +1,106 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use std::time::Duration;
+use structopt::StructOpt;
+use zenoh::net::protocol::core::{Period, SubMode, ZInt};
+
+// The options of the subscriptions of the pong and the subscribers
+#[derive(Debug, StructOpt)]
+pub struct SubOpt {
+    // push or pull
+    #[structopt(long = "sub-mode", default_value = "push")]
+    pub sub_mode: String,
+    // The cadence of the pulls, in seconds
+    #[structopt(long = "pull-interval", default_value = "0.001")]
+    pub pull_interval: f64,
+    // The maximum number of samples delivered per pull, the zenoh-net pulls
+    // deliver all the available samples and the subscriber only takes this
+    // many of them, the others wait for the next pull
+    #[structopt(long = "max-samples")]
+    pub max_samples: Option<ZInt>,
+    // The period of the subscription, in milliseconds
+    #[structopt(long = "period")]
+    pub period: Option<ZInt>,
+}
+
+impl SubOpt {
+    pub fn mode(&self) -> SubMode {
+        match self.sub_mode.as_str() {
+            "push" => SubMode::Push,
+            "pull" => SubMode::Pull,
+            _ => panic!("Unsupported sub mode: {}", self.sub_mode),
+        }
+    }
+
+    pub fn period(&self) -> Option<Period> {
+        self.period.map(|p| Period {
+            origin: 0,
+            period: p,
+            duration: p,
+        })
+    }
+
+    pub fn pull_interval(&self) -> Duration {
+        Duration::from_secs_f64(self.pull_interval)
+    }
+
+    // The number of samples to take from the subscriber on every pull
+    pub fn samples_per_pull(&self) -> usize {
+        self.max_samples.map_or(usize::MAX, |m| m as usize)
+    }
+
+    // The kind of the records, e.g. throughput.pull.periodic for a periodic
+    // pull subscription
+    pub fn kind(&self, base: &str) -> String {
+        match (self.mode(), self.period) {
+            (SubMode::Push, None) => base.to_string(),
+            (_, None) => format!("{}.{}", base, self.sub_mode),
+            (_, Some(_)) => format!("{}.{}.periodic", base, self.sub_mode),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sub_opt(args: &[&str]) -> SubOpt {
+        SubOpt::from_iter(std::iter::once("test").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn kind_of_the_subscriptions() {
+        assert_eq!(sub_opt(&[]).kind("throughput"), "throughput");
+        assert_eq!(
+            sub_opt(&["--sub-mode", "pull"]).kind("throughput"),
+            "throughput.pull"
+        );
+        assert_eq!(
+            sub_opt(&["--sub-mode", "pull", "--period", "10"]).kind("throughput"),
+            "throughput.pull.periodic"
+        );
+    }
+
+    #[test]
+    fn samples_per_pull() {
+        assert_eq!(sub_opt(&[]).samples_per_pull(), usize::MAX);
+        assert_eq!(sub_opt(&["--max-samples", "3"]).samples_per_pull(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported sub mode: poll")]
+    fn rejects_unknown_sub_modes() {
+        sub_opt(&["--sub-mode", "poll"]).mode();
+    }
+}
@@ -0,0 +1,35 @@
+#!/usr/bin/env bash
+
+# Measure the time it takes N peers started at once on loopback to discover
+# and connect to each other, and to receive their first data, with multicast
+# and gossip scouting. Each run is repeated RUNS times.
+#
+# With ROUTER=1 the zn_router of the throughput crate is spawned on
+# ROUTER_LOCATOR and the runs of the router scouting, where all the peers
+# connect to it, are added to the others.
+RUNS=${RUNS:-10}
+TIMEOUT=${TIMEOUT:-10}
+SCOUTINGS=${SCOUTINGS:-multicast gossip}
+ROUTER=${ROUTER:-0}
+ROUTER_LOCATOR=${ROUTER_LOCATOR:-tcp/127.0.0.1:7446}
+
+. ../router.sh
+
+if [ "$ROUTER" = 1 ]; then
+    SCOUTINGS="$SCOUTINGS router"
+fi
+
+for scouting in $SCOUTINGS; do
+    for peers in 2 4 8 16 32; do
+        for run in $(seq 1 $RUNS); do
+            if [ "$scouting" = router ]; then
+                start_router $ROUTER_LOCATOR
+            fi
+            ../target/release/zn_discovery -e $peers --scouting $scouting --router $ROUTER_LOCATOR --timeout $TIMEOUT -n zn_discovery -s discovery.$run
+            if [ "$scouting" = router ]; then
+                print_router_stats discovery.$run,$peers
+                stop_router
+            fi
+        done
+    done
+done
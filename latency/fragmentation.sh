@@ -0,0 +1,51 @@
+#!/usr/bin/env bash
+
+# Sweep the payload from 1 KB to 64 MB over TCP, UDP and Unix sockets to find
+# out where the fragmentation overhead kicks in, the resident memory of the
+# whole ping process is printed every second along with the results, the
+# fragments per message are reported by the sweep of the throughput crate. The
+# unixsock-stream variant requires the binaries to be built with the
+# transport_unixsock-stream feature.
+#
+# With ROUTER=1 the zn_router of the throughput crate is spawned on the locator
+# and both sides connect to it in client mode, its stats are printed at the
+# end of each run.
+DURATION=${DURATION:-10}
+INTERVAL=${INTERVAL:-0.1}
+UNIXSOCK_PATH=${UNIXSOCK_PATH:-/tmp/zenoh-perf.sock}
+PROTOS=${PROTOS:-tcp udp unixsock-stream}
+ROUTER=${ROUTER:-0}
+
+. ../router.sh
+
+for proto in $PROTOS; do
+    case $proto in
+        udp) locator=udp/127.0.0.1:7447 ;;
+        unixsock-stream) locator=unixsock-stream/$UNIXSOCK_PATH ;;
+        *) locator=tcp/127.0.0.1:7447 ;;
+    esac
+    mode=peer
+    if [ "$ROUTER" = 1 ]; then
+        mode=client
+    fi
+    for exp in $(seq 10 26); do
+        payload=$((1 << exp))
+        if [ "$ROUTER" = 1 ]; then
+            start_router $locator
+        fi
+        ../target/release/zn_pong -l $locator -m $mode &
+        pong=$!
+        sleep 1
+        ../target/release/zn_ping -l $locator -m $mode -p $payload -i $INTERVAL -n zn_ping -s fragmentation.$proto --memory &
+        ping=$!
+        sleep $DURATION
+        if [ "$ROUTER" = 1 ]; then
+            print_router_stats fragmentation.$proto,$payload
+        fi
+        kill $ping $pong
+        wait $ping $pong 2>/dev/null
+        if [ "$ROUTER" = 1 ]; then
+            stop_router
+        fi
+    done
+done
@@ -0,0 +1,43 @@
+#!/usr/bin/env bash
+
+# Compare the copy path of zn_ping/zn_pong with the shared memory one of
+# zn_shm_ping/zn_shm_pong for large payloads, from 4 KB to 16 MB, over TCP on
+# loopback. The binaries must be built with the shared-memory feature.
+#
+# With ROUTER=1 the zn_router of the throughput crate is spawned on the locator
+# and both sides connect to it in client mode, its stats are printed at the
+# end of each run.
+DURATION=${DURATION:-10}
+INTERVAL=${INTERVAL:-0.1}
+LOCATOR=${LOCATOR:-tcp/127.0.0.1:7447}
+ROUTER=${ROUTER:-0}
+
+. ../router.sh
+
+mode=peer
+if [ "$ROUTER" = 1 ]; then
+    mode=client
+fi
+
+for exp in $(seq 12 24); do
+    payload=$((1 << exp))
+    for bin in zn zn_shm; do
+        if [ "$ROUTER" = 1 ]; then
+            start_router $LOCATOR
+        fi
+        ../target/release/${bin}_pong -l $LOCATOR -m $mode &
+        pong=$!
+        sleep 1
+        ../target/release/${bin}_ping -l $LOCATOR -m $mode -p $payload -i $INTERVAL -n ${bin}_ping -s shm &
+        ping=$!
+        sleep $DURATION
+        if [ "$ROUTER" = 1 ]; then
+            print_router_stats shm.${bin},$payload
+        fi
+        kill $ping $pong
+        wait $ping $pong 2>/dev/null
+        if [ "$ROUTER" = 1 ]; then
+            stop_router
+        fi
+    done
+done
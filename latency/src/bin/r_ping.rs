@@ -13,7 +13,8 @@
 //
 use async_std::task;
 use std::collections::HashMap;
-use std::sync::{Arc, Barrier, Mutex};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::time::Instant;
 use structopt::StructOpt;
@@ -25,6 +26,9 @@ use zenoh::net::protocol::io::{WBuf, ZBuf};
 use zenoh::net::protocol::proto::{DataInfo, RoutingContext};
 use zenoh::net::protocol::session::Primitives;
 use zenoh::net::runtime::Runtime;
+use zenoh_perf_common::reliability::reliability;
+use zenoh_perf_common::router;
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::properties::config::{
     ConfigProperties, ZN_MODE_KEY, ZN_MULTICAST_SCOUTING_KEY, ZN_PEER_KEY,
 };
@@ -86,7 +90,12 @@ impl Primitives for LatencyPrimitivesParallel {
         let mut count_bytes = [0u8; 8];
         payload.read_bytes(&mut count_bytes);
         let count = u64::from_le_bytes(count_bytes);
-        let instant = self.pending.lock().unwrap().remove(&count).unwrap();
+        let mut guard = self.pending.lock().unwrap();
+        // A late pong whose ping has already been accounted as lost
+        let instant = match guard.remove(&count) {
+            Some(instant) => instant,
+            None => return,
+        };
         println!(
             "router,{},latency.parallel,{},{},{},{},{}",
             self.scenario,
@@ -96,6 +105,20 @@ impl Primitives for LatencyPrimitivesParallel {
             count,
             instant.elapsed().as_micros()
         );
+        // Pongs are received in order, any older pending ping has been lost
+        let mut lost: Vec<u64> = guard.keys().filter(|c| **c < count).copied().collect();
+        lost.sort_unstable();
+        for c in lost {
+            guard.remove(&c);
+            println!(
+                "router,{},latency.parallel.lost,{},{},{},{}",
+                self.scenario,
+                self.name,
+                payload.len(),
+                self.interval,
+                c
+            );
+        }
     }
 
     fn send_query(
@@ -132,11 +155,11 @@ impl Primitives for LatencyPrimitivesParallel {
 
 // Primitives for the blocking locator
 struct LatencyPrimitivesSequential {
-    pending: Arc<Mutex<HashMap<u64, Arc<Barrier>>>>,
+    pending: Arc<Mutex<HashMap<u64, Sender<()>>>>,
 }
 
 impl LatencyPrimitivesSequential {
-    pub fn new(pending: Arc<Mutex<HashMap<u64, Arc<Barrier>>>>) -> Self {
+    pub fn new(pending: Arc<Mutex<HashMap<u64, Sender<()>>>>) -> Self {
         Self { pending }
     }
 }
@@ -174,8 +197,10 @@ impl Primitives for LatencyPrimitivesSequential {
         let mut count_bytes = [0u8; 8];
         payload.read_bytes(&mut count_bytes);
         let count = u64::from_le_bytes(count_bytes);
-        let barrier = self.pending.lock().unwrap().remove(&count).unwrap();
-        barrier.wait();
+        // The ping may have already timed out
+        if let Some(tx) = self.pending.lock().unwrap().remove(&count) {
+            let _ = tx.send(());
+        }
     }
 
     fn send_query(
@@ -227,12 +252,34 @@ struct Opt {
     interval: f64,
     #[structopt(long = "parallel")]
     parallel: bool,
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(long = "timeout", default_value = "1")]
+    timeout: f64,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
-async fn parallel(opt: Opt, config: ConfigProperties) {
+// Start the runtime, in client mode the time it takes includes the handshake
+// with the router since the start waits for the connection
+async fn open_runtime(opt: &Opt, config: ConfigProperties) -> Runtime {
+    let now = Instant::now();
+    let runtime = Runtime::new(0u8, config, None).await.unwrap();
+    if opt.mode == "client" {
+        println!(
+            "router,{},handshake,{},{}",
+            opt.scenario,
+            opt.name,
+            now.elapsed().as_micros()
+        );
+    }
+    runtime
+}
+
+async fn parallel(opt: Opt, config: ConfigProperties, reliability: Reliability) {
     let pending: Arc<Mutex<HashMap<u64, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
 
-    let runtime = Runtime::new(0u8, config, None).await.unwrap();
+    let runtime = open_runtime(&opt, config).await;
     let rx_primitives = Arc::new(LatencyPrimitivesParallel::new(
         opt.scenario,
         opt.name,
@@ -243,7 +290,7 @@ async fn parallel(opt: Opt, config: ConfigProperties) {
 
     let rid = ResKey::RName("/test/pong".to_string());
     let sub_info = SubInfo {
-        reliability: Reliability::Reliable,
+        reliability,
         mode: SubMode::Push,
         period: None,
     };
@@ -251,7 +298,7 @@ async fn parallel(opt: Opt, config: ConfigProperties) {
 
     let channel = Channel {
         priority: Priority::Data,
-        reliability: Reliability::Reliable,
+        reliability,
     };
     let payload = vec![0u8; opt.payload - 8];
     let mut count: u64 = 0;
@@ -274,16 +321,16 @@ async fn parallel(opt: Opt, config: ConfigProperties) {
     }
 }
 
-async fn single(opt: Opt, config: ConfigProperties) {
-    let pending: Arc<Mutex<HashMap<u64, Arc<Barrier>>>> = Arc::new(Mutex::new(HashMap::new()));
+async fn single(opt: Opt, config: ConfigProperties, reliability: Reliability) {
+    let pending: Arc<Mutex<HashMap<u64, Sender<()>>>> = Arc::new(Mutex::new(HashMap::new()));
 
-    let runtime = Runtime::new(0u8, config, None).await.unwrap();
+    let runtime = open_runtime(&opt, config).await;
     let rx_primitives = Arc::new(LatencyPrimitivesSequential::new(pending.clone()));
     let tx_primitives = runtime.router.new_primitives(rx_primitives);
 
     let rid = ResKey::RName("/test/pong".to_string());
     let sub_info = SubInfo {
-        reliability: Reliability::Reliable,
+        reliability,
         mode: SubMode::Push,
         period: None,
     };
@@ -291,8 +338,9 @@ async fn single(opt: Opt, config: ConfigProperties) {
 
     let channel = Channel {
         priority: Priority::Data,
-        reliability: Reliability::Reliable,
+        reliability,
     };
+    let timeout = Duration::from_secs_f64(opt.timeout);
     let payload = vec![0u8; opt.payload - 8];
     let mut count: u64 = 0;
     let reskey = ResKey::RName("/test/ping".to_string());
@@ -305,12 +353,31 @@ async fn single(opt: Opt, config: ConfigProperties) {
         let data: ZBuf = data.into();
 
         // Insert the pending ping
-        let barrier = Arc::new(Barrier::new(2));
-        pending.lock().unwrap().insert(count, barrier.clone());
+        let (tx, rx) = mpsc::channel();
+        pending.lock().unwrap().insert(count, tx);
 
         let now = Instant::now();
         tx_primitives.send_data(&reskey, data, channel, None, None);
-        barrier.wait();
+        // Wait for the pong to arrive, best-effort pings are accounted as lost
+        // if the pong does not arrive before the timeout
+        let res = match reliability {
+            Reliability::Reliable => rx.recv().map_err(|_| ()),
+            Reliability::BestEffort => rx.recv_timeout(timeout).map_err(|_| ()),
+        };
+        if res.is_err() {
+            pending.lock().unwrap().remove(&count);
+            println!(
+                "router,{},latency.sequential.lost,{},{},{},{}",
+                opt.scenario,
+                opt.name,
+                payload.len(),
+                opt.interval,
+                count
+            );
+            task::sleep(Duration::from_secs_f64(opt.interval)).await;
+            count += 1;
+            continue;
+        }
         println!(
             "router,{},latency.sequential,{},{},{},{},{}",
             opt.scenario,
@@ -337,12 +404,18 @@ async fn main() {
     let mut config = ConfigProperties::default();
     config.insert(ZN_MODE_KEY, opt.mode.clone());
 
+    opt.tls.configure_runtime(&mut config);
+
+    router::configure_runtime(&opt.mode, &mut config);
+
     config.insert(ZN_MULTICAST_SCOUTING_KEY, "false".to_string());
     config.insert(ZN_PEER_KEY, opt.locator.clone());
 
+    let reliability = reliability(&opt.reliability);
+
     if opt.parallel {
-        parallel(opt, config).await;
+        parallel(opt, config, reliability).await;
     } else {
-        single(opt, config).await;
+        single(opt, config, reliability).await;
     }
 }
@@ -0,0 +1,278 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::task;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+use zenoh::net::protocol::core::{
+    Channel, PeerId, Priority, QueryConsolidation, QueryTarget, Reliability, ResKey, SubInfo,
+    SubMode, ZInt,
+};
+use zenoh::net::protocol::io::ZBuf;
+use zenoh::net::protocol::proto::{DataInfo, RoutingContext};
+use zenoh::net::protocol::session::Primitives;
+use zenoh::net::runtime::Runtime;
+use zenoh_perf_common::{reliability, router};
+use zenoh_perf_tls::TlsOpt;
+use zenoh_util::properties::config::{
+    ConfigProperties, ZN_MODE_KEY, ZN_MULTICAST_SCOUTING_KEY, ZN_PEER_KEY, ZN_QOS_KEY,
+};
+
+// Primitives for the blocking locator
+struct LatencyPrimitives {
+    pending: Arc<Mutex<HashMap<u64, Sender<()>>>>,
+}
+
+impl LatencyPrimitives {
+    pub fn new(pending: Arc<Mutex<HashMap<u64, Sender<()>>>>) -> Self {
+        Self { pending }
+    }
+}
+
+impl Primitives for LatencyPrimitives {
+    fn decl_resource(&self, _rid: ZInt, _reskey: &ResKey) {}
+    fn forget_resource(&self, _rid: ZInt) {}
+    fn decl_publisher(&self, _reskey: &ResKey, _routing_context: Option<RoutingContext>) {}
+    fn forget_publisher(&self, _reskey: &ResKey, _routing_context: Option<RoutingContext>) {}
+    fn decl_subscriber(
+        &self,
+        _reskey: &ResKey,
+        _sub_info: &SubInfo,
+        _routing_context: Option<RoutingContext>,
+    ) {
+    }
+    fn forget_subscriber(&self, _reskey: &ResKey, _routing_context: Option<RoutingContext>) {}
+    fn decl_queryable(
+        &self,
+        _reskey: &ResKey,
+        _kind: ZInt,
+        _routing_context: Option<RoutingContext>,
+    ) {
+    }
+    fn forget_queryable(&self, _reskey: &ResKey, _routing_context: Option<RoutingContext>) {}
+
+    fn send_data(
+        &self,
+        _reskey: &ResKey,
+        mut payload: ZBuf,
+        _channel: Channel,
+        _data_info: Option<DataInfo>,
+        _routing_context: Option<RoutingContext>,
+    ) {
+        let count = reliability::payload_count(&mut payload);
+        if count == reliability::BACKGROUND {
+            return;
+        }
+        if let Some(tx) = self.pending.lock().unwrap().remove(&count) {
+            let _ = tx.send(());
+        }
+    }
+
+    fn send_query(
+        &self,
+        _reskey: &ResKey,
+        _predicate: &str,
+        _qid: ZInt,
+        _target: QueryTarget,
+        _consolidation: QueryConsolidation,
+        _routing_context: Option<RoutingContext>,
+    ) {
+    }
+    fn send_reply_data(
+        &self,
+        _qid: ZInt,
+        _source_kind: ZInt,
+        _replier_id: PeerId,
+        _reskey: ResKey,
+        _info: Option<DataInfo>,
+        _payload: ZBuf,
+    ) {
+    }
+    fn send_reply_final(&self, _qid: ZInt) {}
+    fn send_pull(
+        &self,
+        _is_final: bool,
+        _reskey: &ResKey,
+        _pull_id: ZInt,
+        _max_samples: &Option<ZInt>,
+    ) {
+    }
+    fn send_close(&self) {}
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "r_ping_qos")]
+struct Opt {
+    #[structopt(short = "l", long = "locator")]
+    locator: String,
+    #[structopt(short = "m", long = "mode")]
+    mode: String,
+    #[structopt(short = "p", long = "payload")]
+    payload: usize,
+    #[structopt(short = "n", long = "name")]
+    name: String,
+    #[structopt(short = "s", long = "scenario")]
+    scenario: String,
+    #[structopt(short = "i", long = "interval")]
+    interval: f64,
+    #[structopt(long = "priority", default_value = "real-time")]
+    priority: String,
+    #[structopt(long = "background-priority", default_value = "background")]
+    background_priority: String,
+    #[structopt(long = "background-payload", default_value = "8192")]
+    background_payload: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
+}
+
+#[async_std::main]
+async fn main() {
+    // Enable logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    // The count is written on the first 8 bytes
+    if opt.payload < 8 {
+        panic!("Unsupported payload: {}", opt.payload);
+    }
+    if opt.background_payload < 8 {
+        panic!("Unsupported background payload: {}", opt.background_payload);
+    }
+
+    let mut config = ConfigProperties::default();
+    config.insert(ZN_MODE_KEY, opt.mode.clone());
+
+    opt.tls.configure_runtime(&mut config);
+
+    router::configure_runtime(&opt.mode, &mut config);
+
+    // The priorities are only honoured over a session with QoS
+    config.insert(ZN_QOS_KEY, "true".to_string());
+    config.insert(ZN_MULTICAST_SCOUTING_KEY, "false".to_string());
+    config.insert(ZN_PEER_KEY, opt.locator.clone());
+
+    let priorities: Vec<(String, Priority)> = opt
+        .priority
+        .split(',')
+        .map(|p| (p.to_string(), reliability::priority(p)))
+        .collect();
+    let background_priority = reliability::priority(&opt.background_priority);
+
+    let pending: Arc<Mutex<HashMap<u64, Sender<()>>>> = Arc::new(Mutex::new(HashMap::new()));
+    let runtime = Runtime::new(0u8, config, None).await.unwrap();
+    // Wait for the session to the pong, QoS is negotiated with it and it
+    // enables it by default
+    let sessions = loop {
+        let sessions = runtime.manager().get_sessions().await;
+        if !sessions.is_empty() {
+            break sessions;
+        }
+        task::sleep(Duration::from_millis(100)).await;
+    };
+    for session in sessions.iter() {
+        if !session.is_qos().unwrap() {
+            panic!("QoS is not enabled on the session to {}", opt.locator);
+        }
+    }
+    let rx_primitives = Arc::new(LatencyPrimitives::new(pending.clone()));
+    let tx_primitives = runtime.router.new_primitives(rx_primitives);
+
+    let rid = ResKey::RName("/test/pong".to_string());
+    let sub_info = SubInfo {
+        reliability: Reliability::Reliable,
+        mode: SubMode::Push,
+        period: None,
+    };
+    tx_primitives.decl_subscriber(&rid, &sub_info, None);
+
+    // Saturate the face with background messages on the low priority
+    let background = reliability::count_payload(reliability::BACKGROUND, opt.background_payload);
+    let counter = Arc::new(AtomicUsize::new(0));
+    let c_counter = counter.clone();
+    let c_tx_primitives = tx_primitives.clone();
+    // The messages are routed in the calls, which block when the queue is full
+    task::spawn_blocking(move || {
+        let channel = Channel {
+            priority: background_priority,
+            reliability: Reliability::Reliable,
+        };
+        let reskey = ResKey::RName("/test/ping".to_string());
+        loop {
+            c_tx_primitives.send_data(&reskey, background.clone(), channel, None, None);
+            c_counter.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+
+    let scenario = opt.scenario.clone();
+    let name = opt.name.clone();
+    let background_payload = opt.background_payload;
+    let c_background_priority = opt.background_priority.clone();
+    task::spawn(async move {
+        loop {
+            let now = Instant::now();
+            task::sleep(Duration::from_secs(1)).await;
+            let elapsed = now.elapsed().as_micros() as f64;
+
+            let c = counter.swap(0, Ordering::Relaxed);
+            let interval = 1_000_000.0 / elapsed;
+            println!(
+                "router,{},qos.background,{},{},{},{}",
+                scenario,
+                name,
+                background_payload,
+                c_background_priority,
+                (c as f64 / interval).floor() as usize
+            );
+        }
+    });
+
+    let sleep = Duration::from_secs_f64(opt.interval);
+    let reskey = ResKey::RName("/test/ping".to_string());
+    let mut count: u64 = 0;
+    loop {
+        let (priority_name, priority) = &priorities[count as usize % priorities.len()];
+        let channel = Channel {
+            priority: *priority,
+            reliability: Reliability::Reliable,
+        };
+        let data = reliability::count_payload(count, opt.payload);
+
+        // Insert the pending ping
+        let (tx, rx) = mpsc::channel();
+        pending.lock().unwrap().insert(count, tx);
+        let now = Instant::now();
+        tx_primitives.send_data(&reskey, data, channel, None, None);
+        // Wait for the pong to arrive
+        rx.recv().unwrap();
+        println!(
+            "router,{},latency.qos,{},{},{},{},{},{},{}",
+            opt.scenario,
+            opt.name,
+            opt.payload,
+            opt.interval,
+            priority_name,
+            opt.background_priority,
+            count,
+            now.elapsed().as_micros()
+        );
+
+        task::sleep(sleep).await;
+        count += 1;
+    }
+}
@@ -12,16 +12,20 @@
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
 use async_std::future;
+use async_std::task;
 use std::sync::{Arc, Mutex};
 use structopt::StructOpt;
 use zenoh::net::protocol::core::{
-    Channel, PeerId, QueryConsolidation, QueryTarget, Reliability, ResKey, SubInfo, SubMode, ZInt,
+    Channel, PeerId, QueryConsolidation, QueryTarget, ResKey, SubInfo, SubMode, ZInt,
 };
 use zenoh::net::protocol::io::ZBuf;
 use zenoh::net::protocol::proto::{DataInfo, RoutingContext};
 use zenoh::net::protocol::session::Primitives;
 use zenoh::net::routing::face::Face;
 use zenoh::net::runtime::Runtime;
+use zenoh_perf_common::reliability::reliability;
+use zenoh_perf_common::sub::SubOpt;
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::properties::config::{
     ConfigProperties, ZN_LISTENER_KEY, ZN_MODE_KEY, ZN_MULTICAST_SCOUTING_KEY, ZN_PEER_KEY,
 };
@@ -122,6 +126,12 @@ struct Opt {
     locator: String,
     #[structopt(short = "m", long = "mode")]
     mode: String,
+    #[structopt(flatten)]
+    sub: SubOpt,
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -135,6 +145,8 @@ async fn main() {
     let mut config = ConfigProperties::default();
     config.insert(ZN_MODE_KEY, opt.mode.clone());
 
+    opt.tls.configure_runtime(&mut config);
+
     config.insert(ZN_MULTICAST_SCOUTING_KEY, "false".to_string());
     match opt.mode.as_str() {
         "peer" | "router" => config.insert(ZN_LISTENER_KEY, opt.locator),
@@ -147,14 +159,29 @@ async fn main() {
     let tx_primitives = runtime.router.new_primitives(rx_primitives.clone());
     rx_primitives.set_tx(tx_primitives.clone());
 
+    let mode = opt.sub.mode();
+    let period = opt.sub.period();
+
+    let reliability = reliability(&opt.reliability);
     let rid = ResKey::RName("/test/ping".to_string());
     let sub_info = SubInfo {
-        reliability: Reliability::Reliable,
-        mode: SubMode::Push,
-        period: None,
+        reliability,
+        mode,
+        period,
     };
     tx_primitives.decl_subscriber(&rid, &sub_info, None);
 
+    if sub_info.mode == SubMode::Pull {
+        // Pull the available pings at the given cadence
+        let sleep = opt.sub.pull_interval();
+        let mut pull_id: ZInt = 0;
+        loop {
+            tx_primitives.send_pull(true, &rid, pull_id, &opt.sub.max_samples);
+            task::sleep(sleep).await;
+            pull_id += 1;
+        }
+    }
+
     // Stop forever
     future::pending::<()>().await;
 }
@@ -0,0 +1,311 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::io;
+use async_std::net::{SocketAddr, TcpStream, UdpSocket};
+#[cfg(all(unix, feature = "transport_unixsock-stream"))]
+use async_std::os::unix::net::UnixStream;
+use async_std::prelude::*;
+use async_std::sync::Arc;
+use async_std::task;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+
+// The largest UDP payload over IPv4
+const MAX_DATAGRAM: usize = 65_507;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "raw_ping")]
+struct Opt {
+    // tcp, udp, or unixsock-stream when built with the
+    // transport_unixsock-stream feature, the locator being the socket path
+    #[structopt(short = "t", long = "transport", default_value = "tcp")]
+    transport: String,
+    #[structopt(short = "l", long = "locator")]
+    locator: String,
+    #[structopt(short = "p", long = "payload")]
+    payload: usize,
+    #[structopt(short = "n", long = "name")]
+    name: String,
+    #[structopt(short = "s", long = "scenario")]
+    scenario: String,
+    #[structopt(short = "i", long = "interval")]
+    interval: f64,
+    #[structopt(long = "parallel")]
+    parallel: bool,
+    // Seconds after which a ping over UDP is accounted as lost
+    #[structopt(long = "timeout", default_value = "1")]
+    timeout: f64,
+}
+
+// Write a message prefixed by its length on 16 bits, mirroring the framing of
+// the zenoh batches
+async fn send_framed<S: io::Write + Unpin>(stream: &mut S, data: &[u8]) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(2 + data.len());
+    bytes.extend_from_slice(&(data.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(data);
+    stream.write_all(&bytes).await
+}
+
+async fn recv_framed<S: io::Read + Unpin>(stream: &mut S, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut length = [0u8; 2];
+    stream.read_exact(&mut length).await?;
+    let length = u16::from_le_bytes(length) as usize;
+    let buffer = buffer.get_mut(..length).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unexpected message length: {}", length),
+        )
+    })?;
+    stream.read_exact(buffer).await?;
+    Ok(length)
+}
+
+// The bare transport the pings are sent over, the messages over the streams
+// are framed
+#[derive(Clone)]
+enum Link {
+    Tcp(TcpStream),
+    Udp(Arc<UdpSocket>),
+    #[cfg(all(unix, feature = "transport_unixsock-stream"))]
+    Unix(UnixStream),
+}
+
+impl Link {
+    async fn connect(transport: &str, locator: &str) -> Self {
+        match transport {
+            "tcp" => {
+                let stream = TcpStream::connect(locator).await.unwrap();
+                stream.set_nodelay(true).unwrap();
+                Link::Tcp(stream)
+            }
+            "udp" => {
+                let locator: SocketAddr = locator.parse().unwrap();
+                let local: SocketAddr = if locator.is_ipv4() {
+                    "0.0.0.0:0".parse().unwrap()
+                } else {
+                    "[::]:0".parse().unwrap()
+                };
+                let socket = UdpSocket::bind(local).await.unwrap();
+                socket.connect(locator).await.unwrap();
+                Link::Udp(Arc::new(socket))
+            }
+            #[cfg(all(unix, feature = "transport_unixsock-stream"))]
+            "unixsock-stream" => Link::Unix(UnixStream::connect(locator).await.unwrap()),
+            _ => panic!("Unsupported transport: {}", transport),
+        }
+    }
+
+    fn layer(&self) -> &'static str {
+        match self {
+            Link::Tcp(_) => "raw-tcp",
+            Link::Udp(_) => "raw-udp",
+            #[cfg(all(unix, feature = "transport_unixsock-stream"))]
+            Link::Unix(_) => "raw-unixsock-stream",
+        }
+    }
+
+    async fn send(&mut self, data: &[u8]) -> io::Result<()> {
+        match self {
+            Link::Tcp(stream) => send_framed(stream, data).await,
+            // The datagrams refused by a pong not yet started are lost
+            Link::Udp(socket) => match socket.send(data).await {
+                Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => Ok(()),
+                res => res.map(|_| ()),
+            },
+            #[cfg(all(unix, feature = "transport_unixsock-stream"))]
+            Link::Unix(stream) => send_framed(stream, data).await,
+        }
+    }
+
+    async fn recv(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Link::Tcp(stream) => recv_framed(stream, buffer).await,
+            Link::Udp(socket) => loop {
+                match socket.recv(buffer).await {
+                    Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => continue,
+                    res => break res,
+                }
+            },
+            #[cfg(all(unix, feature = "transport_unixsock-stream"))]
+            Link::Unix(stream) => recv_framed(stream, buffer).await,
+        }
+    }
+
+    // Over the streams the pongs cannot be lost, they are waited for forever
+    async fn recv_timeout(&mut self, buffer: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        if let Link::Udp(_) = self {
+            return io::timeout(timeout, self.recv(buffer)).await;
+        }
+        self.recv(buffer).await
+    }
+}
+
+fn ping(payload: usize, count: u64) -> Vec<u8> {
+    let mut data = vec![0u8; payload];
+    data[..8].copy_from_slice(&count.to_le_bytes());
+    data
+}
+
+fn count(data: &[u8]) -> u64 {
+    let mut count_bytes = [0u8; 8];
+    count_bytes.copy_from_slice(&data[..8]);
+    u64::from_le_bytes(count_bytes)
+}
+
+async fn single(opt: Opt, mut link: Link) {
+    let layer = link.layer();
+    let sleep = Duration::from_secs_f64(opt.interval);
+    let timeout = Duration::from_secs_f64(opt.timeout);
+    let mut buffer = vec![0u8; opt.payload];
+    let mut c: u64 = 0;
+    loop {
+        let data = ping(opt.payload, c);
+
+        let now = Instant::now();
+        link.send(&data).await.unwrap();
+
+        // Wait for the pong to arrive, pings that may be lost are accounted
+        // as such if the pong does not arrive before the timeout
+        let mut pong = None;
+        while pong.is_none() {
+            let n = match link.recv_timeout(&mut buffer, timeout).await {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => break,
+                Err(e) => panic!("{}", e),
+            };
+            // Discard the late pongs of the pings already accounted as lost
+            if n >= 8 && count(&buffer[..n]) == c {
+                pong = Some(n - 8);
+            }
+        }
+        match pong {
+            Some(n) => println!(
+                "{},{},latency.sequential,{},{},{},{},{}",
+                layer,
+                opt.scenario,
+                opt.name,
+                n,
+                opt.interval,
+                c,
+                now.elapsed().as_micros()
+            ),
+            None => println!(
+                "{},{},latency.sequential.lost,{},{},{},{}",
+                layer,
+                opt.scenario,
+                opt.name,
+                opt.payload - 8,
+                opt.interval,
+                c
+            ),
+        }
+
+        task::sleep(sleep).await;
+        c += 1;
+    }
+}
+
+async fn parallel(opt: Opt, mut link: Link) {
+    let layer = link.layer();
+
+    // The hashmap with the pings
+    let pending = Arc::new(Mutex::new(HashMap::<u64, Instant>::new()));
+
+    let c_pending = pending.clone();
+    let mut c_link = link.clone();
+    let scenario = opt.scenario.clone();
+    let name = opt.name.clone();
+    let interval = opt.interval;
+    let payload = opt.payload;
+    task::spawn(async move {
+        let mut buffer = vec![0u8; payload];
+        while let Ok(n) = c_link.recv(&mut buffer).await {
+            if n < 8 {
+                continue;
+            }
+            let c = count(&buffer[..n]);
+            let n = n - 8;
+            let mut guard = c_pending.lock().unwrap();
+            // A late pong whose ping has already been accounted as lost
+            let instant = match guard.remove(&c) {
+                Some(instant) => instant,
+                None => continue,
+            };
+            println!(
+                "{},{},latency.parallel,{},{},{},{},{}",
+                layer,
+                scenario,
+                name,
+                n,
+                interval,
+                c,
+                instant.elapsed().as_micros()
+            );
+            // Pongs are received in order, any older pending ping has been lost
+            let mut lost: Vec<u64> = guard.keys().filter(|p| **p < c).copied().collect();
+            lost.sort_unstable();
+            for p in lost {
+                guard.remove(&p);
+                println!(
+                    "{},{},latency.parallel.lost,{},{},{},{}",
+                    layer, scenario, name, n, interval, p
+                );
+            }
+        }
+    });
+
+    let sleep = Duration::from_secs_f64(opt.interval);
+    let mut c: u64 = 0;
+    loop {
+        let data = ping(opt.payload, c);
+
+        pending.lock().unwrap().insert(c, Instant::now());
+        link.send(&data).await.unwrap();
+
+        task::sleep(sleep).await;
+        c += 1;
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // initiate logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    // The count is written on the first 8 bytes, the reported payloads are
+    // the bytes that follow as in zn_ping. The stream messages are framed by
+    // their length on 16 bits and the datagrams are bounded by UDP
+    let max = if opt.transport == "udp" {
+        MAX_DATAGRAM
+    } else {
+        u16::MAX as usize
+    };
+    if opt.payload < 8 || opt.payload > max {
+        panic!("Unsupported payload: {}", opt.payload);
+    }
+
+    let link = Link::connect(&opt.transport, &opt.locator).await;
+
+    if opt.parallel {
+        parallel(opt, link).await;
+        return;
+    }
+
+    single(opt, link).await;
+}
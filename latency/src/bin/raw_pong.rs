@@ -0,0 +1,98 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::io;
+use async_std::net::{TcpListener, UdpSocket};
+#[cfg(all(unix, feature = "transport_unixsock-stream"))]
+use async_std::os::unix::net::UnixListener;
+use async_std::prelude::*;
+use async_std::task;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "raw_pong")]
+struct Opt {
+    // tcp, udp, or unixsock-stream when built with the
+    // transport_unixsock-stream feature, the locator being the socket path
+    #[structopt(short = "t", long = "transport", default_value = "tcp")]
+    transport: String,
+    #[structopt(short = "l", long = "locator")]
+    locator: String,
+}
+
+// Echo the messages back, each prefixed by its length on 16 bits
+async fn pong_stream<S: io::Read + io::Write + Unpin>(mut stream: S) {
+    let mut buffer = vec![0u8; 2 + u16::MAX as usize];
+    loop {
+        if stream.read_exact(&mut buffer[..2]).await.is_err() {
+            break;
+        }
+        let length = u16::from_le_bytes([buffer[0], buffer[1]]) as usize;
+        if stream.read_exact(&mut buffer[2..2 + length]).await.is_err() {
+            break;
+        }
+        if stream.write_all(&buffer[..2 + length]).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn pong_tcp(locator: &str) {
+    let listener = TcpListener::bind(locator).await.unwrap();
+    let mut incoming = listener.incoming();
+    while let Some(stream) = incoming.next().await {
+        let stream = stream.unwrap();
+        stream.set_nodelay(true).unwrap();
+        task::spawn(pong_stream(stream));
+    }
+}
+
+#[cfg(all(unix, feature = "transport_unixsock-stream"))]
+async fn pong_unix(locator: &str) {
+    // Remove the socket file left over by a previous run
+    let _ = std::fs::remove_file(locator);
+    let listener = UnixListener::bind(locator).await.unwrap();
+    let mut incoming = listener.incoming();
+    while let Some(stream) = incoming.next().await {
+        task::spawn(pong_stream(stream.unwrap()));
+    }
+}
+
+async fn pong_udp(locator: &str) {
+    let socket = UdpSocket::bind(locator).await.unwrap();
+    let mut buffer = vec![0u8; 65_536];
+    loop {
+        let (n, peer) = socket.recv_from(&mut buffer).await.unwrap();
+        // The ping accounts the pong as lost
+        if let Err(e) = socket.send_to(&buffer[..n], peer).await {
+            log::warn!("Failed to send a pong to {}: {}", peer, e);
+        }
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // initiate logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    match opt.transport.as_str() {
+        "tcp" => pong_tcp(&opt.locator).await,
+        "udp" => pong_udp(&opt.locator).await,
+        #[cfg(all(unix, feature = "transport_unixsock-stream"))]
+        "unixsock-stream" => pong_unix(&opt.locator).await,
+        _ => panic!("Unsupported transport: {}", opt.transport),
+    }
+}
@@ -15,7 +15,8 @@ use async_std::task;
 use rand::RngCore;
 use std::any::Any;
 use std::collections::HashMap;
-use std::sync::{Arc, Barrier, Mutex};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 use zenoh::net::protocol::core::{
@@ -27,6 +28,8 @@ use zenoh::net::protocol::proto::{Data, ZenohBody, ZenohMessage};
 use zenoh::net::protocol::session::{
     Session, SessionEventHandler, SessionHandler, SessionManager, SessionManagerConfig,
 };
+use zenoh_perf_common::reliability::reliability;
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::core::ZResult;
 
 // Session Handler for the non-blocking locator
@@ -98,7 +101,12 @@ impl SessionEventHandler for MyMHParallel {
                 let mut count_bytes = [0u8; 8];
                 payload.read_bytes(&mut count_bytes);
                 let count = u64::from_le_bytes(count_bytes);
-                let instant = self.pending.lock().unwrap().remove(&count).unwrap();
+                let mut guard = self.pending.lock().unwrap();
+                // A late pong whose ping has already been accounted as lost
+                let instant = match guard.remove(&count) {
+                    Some(instant) => instant,
+                    None => return Ok(()),
+                };
                 println!(
                     "session,{},latency.parallel,{},{},{},{},{}",
                     self.scenario,
@@ -108,6 +116,20 @@ impl SessionEventHandler for MyMHParallel {
                     count,
                     instant.elapsed().as_micros()
                 );
+                // Pongs are received in order, any older pending ping has been lost
+                let mut lost: Vec<u64> = guard.keys().filter(|c| **c < count).copied().collect();
+                lost.sort_unstable();
+                for c in lost {
+                    guard.remove(&c);
+                    println!(
+                        "session,{},latency.parallel.lost,{},{},{},{}",
+                        self.scenario,
+                        self.name,
+                        payload.len(),
+                        self.interval,
+                        c
+                    );
+                }
             }
             _ => panic!("Invalid message"),
         }
@@ -125,11 +147,11 @@ impl SessionEventHandler for MyMHParallel {
 
 // Session Handler for the blocking locator
 struct MySHSequential {
-    pending: Arc<Mutex<HashMap<u64, Arc<Barrier>>>>,
+    pending: Arc<Mutex<HashMap<u64, Sender<()>>>>,
 }
 
 impl MySHSequential {
-    fn new(pending: Arc<Mutex<HashMap<u64, Arc<Barrier>>>>) -> Self {
+    fn new(pending: Arc<Mutex<HashMap<u64, Sender<()>>>>) -> Self {
         Self { pending }
     }
 }
@@ -145,11 +167,11 @@ impl SessionHandler for MySHSequential {
 
 // Message Handler for the locator
 struct MyMHSequential {
-    pending: Arc<Mutex<HashMap<u64, Arc<Barrier>>>>,
+    pending: Arc<Mutex<HashMap<u64, Sender<()>>>>,
 }
 
 impl MyMHSequential {
-    fn new(pending: Arc<Mutex<HashMap<u64, Arc<Barrier>>>>) -> Self {
+    fn new(pending: Arc<Mutex<HashMap<u64, Sender<()>>>>) -> Self {
         Self { pending }
     }
 }
@@ -161,8 +183,10 @@ impl SessionEventHandler for MyMHSequential {
                 let mut count_bytes = [0u8; 8];
                 payload.read_bytes(&mut count_bytes);
                 let count = u64::from_le_bytes(count_bytes);
-                let barrier = self.pending.lock().unwrap().remove(&count).unwrap();
-                barrier.wait();
+                // The ping may have already timed out
+                if let Some(tx) = self.pending.lock().unwrap().remove(&count) {
+                    let _ = tx.send(());
+                }
             }
             _ => panic!("Invalid message"),
         }
@@ -195,29 +219,50 @@ struct Opt {
     interval: f64,
     #[structopt(long = "parallel")]
     parallel: bool,
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(long = "timeout", default_value = "1")]
+    timeout: f64,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
-async fn single(opt: Opt, whatami: WhatAmI, pid: PeerId) {
-    let pending: Arc<Mutex<HashMap<u64, Arc<Barrier>>>> = Arc::new(Mutex::new(HashMap::new()));
+// Open the session, the time it takes includes the handshake of the link
+async fn open_session(manager: &SessionManager, opt: &Opt) -> Session {
+    let now = Instant::now();
+    let session = manager.open_session(&opt.locator).await.unwrap();
+    println!(
+        "session,{},handshake,{},{}",
+        opt.scenario,
+        opt.name,
+        now.elapsed().as_micros()
+    );
+    session
+}
+
+async fn single(opt: Opt, whatami: WhatAmI, pid: PeerId, reliability: Reliability) {
+    let pending: Arc<Mutex<HashMap<u64, Sender<()>>>> = Arc::new(Mutex::new(HashMap::new()));
+    let opt_config = opt.tls.manager_config().await;
     let config = SessionManagerConfig {
         version: 0,
         whatami,
         id: pid,
         handler: Arc::new(MySHSequential::new(pending.clone())),
     };
-    let manager = SessionManager::new(config, None);
+    let manager = SessionManager::new(config, opt_config);
 
     // Connect to publisher
-    let session = manager.open_session(&opt.locator).await.unwrap();
+    let session = open_session(&manager, &opt).await;
 
     let sleep = Duration::from_secs_f64(opt.interval);
+    let timeout = Duration::from_secs_f64(opt.timeout);
     let payload = vec![0u8; opt.payload - 8];
     let mut count: u64 = 0;
     loop {
         // Create and send the message
         let channel = Channel {
             priority: Priority::Data,
-            reliability: Reliability::Reliable,
+            reliability,
         };
         let key = ResKey::RName("/test/ping".to_string());
         let info = None;
@@ -242,12 +287,30 @@ async fn single(opt: Opt, whatami: WhatAmI, pid: PeerId) {
         );
 
         // Insert the pending ping
-        let barrier = Arc::new(Barrier::new(2));
-        pending.lock().unwrap().insert(count, barrier.clone());
+        let (tx, rx) = mpsc::channel();
+        pending.lock().unwrap().insert(count, tx);
         let now = Instant::now();
         session.handle_message(message).unwrap();
-        // Wait for the pong to arrive
-        barrier.wait();
+        // Wait for the pong to arrive, best-effort pings are accounted as lost
+        // if the pong does not arrive before the timeout
+        let res = match reliability {
+            Reliability::Reliable => rx.recv().map_err(|_| ()),
+            Reliability::BestEffort => rx.recv_timeout(timeout).map_err(|_| ()),
+        };
+        if res.is_err() {
+            pending.lock().unwrap().remove(&count);
+            println!(
+                "session,{},latency.sequential.lost,{},{},{},{}",
+                opt.scenario,
+                opt.name,
+                payload.len(),
+                opt.interval,
+                count
+            );
+            task::sleep(sleep).await;
+            count += 1;
+            continue;
+        }
         println!(
             "session,{},latency.sequential,{},{},{},{},{}",
             opt.scenario,
@@ -263,23 +326,24 @@ async fn single(opt: Opt, whatami: WhatAmI, pid: PeerId) {
     }
 }
 
-async fn parallel(opt: Opt, whatami: WhatAmI, pid: PeerId) {
+async fn parallel(opt: Opt, whatami: WhatAmI, pid: PeerId, reliability: Reliability) {
     let pending: Arc<Mutex<HashMap<u64, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let opt_config = opt.tls.manager_config().await;
     let config = SessionManagerConfig {
         version: 0,
         whatami,
         id: pid,
         handler: Arc::new(MySHParallel::new(
-            opt.scenario,
-            opt.name,
+            opt.scenario.clone(),
+            opt.name.clone(),
             opt.interval,
             pending.clone(),
         )),
     };
-    let manager = SessionManager::new(config, None);
+    let manager = SessionManager::new(config, opt_config);
 
     // Connect to publisher
-    let session = manager.open_session(&opt.locator).await.unwrap();
+    let session = open_session(&manager, &opt).await;
 
     let sleep = Duration::from_secs_f64(opt.interval);
     let payload = vec![0u8; opt.payload - 8];
@@ -288,7 +352,7 @@ async fn parallel(opt: Opt, whatami: WhatAmI, pid: PeerId) {
         // Create and send the message
         let channel = Channel {
             priority: Priority::Data,
-            reliability: Reliability::Reliable,
+            reliability,
         };
         let key = ResKey::RName("/test/ping".to_string());
         let info = None;
@@ -333,6 +397,7 @@ async fn main() {
     let whatami = match opt.mode.as_str() {
         "peer" => whatami::PEER,
         "client" => whatami::CLIENT,
+        "router" => whatami::ROUTER,
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
 
@@ -341,9 +406,11 @@ async fn main() {
     rand::thread_rng().fill_bytes(&mut pid);
     let pid = PeerId::new(1, pid);
 
+    let reliability = reliability(&opt.reliability);
+
     if opt.parallel {
-        parallel(opt, whatami, pid).await;
+        parallel(opt, whatami, pid, reliability).await;
     } else {
-        single(opt, whatami, pid).await;
+        single(opt, whatami, pid, reliability).await;
     }
 }
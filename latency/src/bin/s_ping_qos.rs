@@ -0,0 +1,264 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::task;
+use rand::RngCore;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+use zenoh::net::protocol::core::{whatami, Channel, PeerId, Priority, Reliability, ResKey};
+use zenoh::net::protocol::link::{Link, Locator};
+use zenoh::net::protocol::proto::{Data, ZenohBody, ZenohMessage};
+use zenoh::net::protocol::session::{
+    Session, SessionEventHandler, SessionHandler, SessionManager, SessionManagerConfig,
+};
+use zenoh_perf_common::reliability;
+use zenoh_perf_tls::TlsOpt;
+use zenoh_util::core::ZResult;
+use zenoh_util::properties::Properties;
+
+// Session Handler for the blocking locator
+struct MySH {
+    pending: Arc<Mutex<HashMap<u64, Sender<()>>>>,
+}
+
+impl MySH {
+    fn new(pending: Arc<Mutex<HashMap<u64, Sender<()>>>>) -> Self {
+        Self { pending }
+    }
+}
+
+impl SessionHandler for MySH {
+    fn new_session(
+        &self,
+        _session: Session,
+    ) -> ZResult<Arc<dyn SessionEventHandler + Send + Sync>> {
+        Ok(Arc::new(MyMH::new(self.pending.clone())))
+    }
+}
+
+// Message Handler for the locator
+struct MyMH {
+    pending: Arc<Mutex<HashMap<u64, Sender<()>>>>,
+}
+
+impl MyMH {
+    fn new(pending: Arc<Mutex<HashMap<u64, Sender<()>>>>) -> Self {
+        Self { pending }
+    }
+}
+
+impl SessionEventHandler for MyMH {
+    fn handle_message(&self, message: ZenohMessage) -> ZResult<()> {
+        match message.body {
+            ZenohBody::Data(Data { mut payload, .. }) => {
+                let count = reliability::payload_count(&mut payload);
+                if count == reliability::BACKGROUND {
+                    return Ok(());
+                }
+                if let Some(tx) = self.pending.lock().unwrap().remove(&count) {
+                    let _ = tx.send(());
+                }
+            }
+            _ => panic!("Invalid message"),
+        }
+        Ok(())
+    }
+
+    fn new_link(&self, _link: Link) {}
+    fn del_link(&self, _link: Link) {}
+    fn closing(&self) {}
+    fn closed(&self) {}
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "s_ping_qos")]
+struct Opt {
+    #[structopt(short = "l", long = "locator")]
+    locator: Locator,
+    #[structopt(short = "m", long = "mode")]
+    mode: String,
+    #[structopt(short = "p", long = "payload")]
+    payload: usize,
+    #[structopt(short = "n", long = "name")]
+    name: String,
+    #[structopt(short = "s", long = "scenario")]
+    scenario: String,
+    #[structopt(short = "i", long = "interval")]
+    interval: f64,
+    #[structopt(long = "priority", default_value = "real-time")]
+    priority: String,
+    #[structopt(long = "background-priority", default_value = "background")]
+    background_priority: String,
+    #[structopt(long = "background-payload", default_value = "8192")]
+    background_payload: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
+}
+
+fn make_message(count: u64, payload: usize, priority: Priority) -> ZenohMessage {
+    let channel = Channel {
+        priority,
+        reliability: Reliability::Reliable,
+    };
+    let key = ResKey::RName("/test/ping".to_string());
+    let info = None;
+
+    let data = reliability::count_payload(count, payload);
+    let routing_context = None;
+    let reply_context = None;
+    let attachment = None;
+
+    ZenohMessage::make_data(
+        key,
+        data,
+        channel,
+        info,
+        routing_context,
+        reply_context,
+        attachment,
+    )
+}
+
+#[async_std::main]
+async fn main() {
+    // Enable logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    // The count is written on the first 8 bytes
+    if opt.payload < 8 {
+        panic!("Unsupported payload: {}", opt.payload);
+    }
+    if opt.background_payload < 8 {
+        panic!("Unsupported background payload: {}", opt.background_payload);
+    }
+
+    let whatami = match opt.mode.as_str() {
+        "peer" => whatami::PEER,
+        "client" => whatami::CLIENT,
+        "router" => whatami::ROUTER,
+        _ => panic!("Unsupported mode: {}", opt.mode),
+    };
+
+    let priorities: Vec<(String, Priority)> = opt
+        .priority
+        .split(',')
+        .map(|p| (p.to_string(), reliability::priority(p)))
+        .collect();
+    let background_priority = reliability::priority(&opt.background_priority);
+
+    // Initialize the Peer Id
+    let mut pid = [0u8; PeerId::MAX_SIZE];
+    rand::thread_rng().fill_bytes(&mut pid);
+    let pid = PeerId::new(1, pid);
+
+    let pending: Arc<Mutex<HashMap<u64, Sender<()>>>> = Arc::new(Mutex::new(HashMap::new()));
+    // The priorities are only honoured over a session with QoS
+    let mut properties = Properties::default();
+    properties.insert("qos".to_string(), "true".to_string());
+    let opt_config = opt.tls.manager_config_with(properties).await;
+    let config = SessionManagerConfig {
+        version: 0,
+        whatami,
+        id: pid,
+        handler: Arc::new(MySH::new(pending.clone())),
+    };
+    let manager = SessionManager::new(config, opt_config);
+
+    // Connect to the pong
+    let session = manager.open_session(&opt.locator).await.unwrap();
+    // QoS is negotiated with the pong, which enables it by default
+    if !session.is_qos().unwrap() {
+        panic!("QoS is not enabled on the session to {}", opt.locator);
+    }
+
+    // Saturate the session with background messages on the low priority
+    let background = make_message(
+        reliability::BACKGROUND,
+        opt.background_payload,
+        background_priority,
+    );
+    let counter = Arc::new(AtomicUsize::new(0));
+    let c_counter = counter.clone();
+    let c_session = session.clone();
+    // The messages are pushed until the queue is full, which blocks
+    task::spawn_blocking(move || loop {
+        let res = c_session.handle_message(background.clone());
+        if res.is_err() {
+            break;
+        }
+        c_counter.fetch_add(1, Ordering::Relaxed);
+    });
+
+    let scenario = opt.scenario.clone();
+    let name = opt.name.clone();
+    let background_payload = opt.background_payload;
+    let c_background_priority = opt.background_priority.clone();
+    task::spawn(async move {
+        loop {
+            let now = Instant::now();
+            task::sleep(Duration::from_secs(1)).await;
+            let elapsed = now.elapsed().as_micros() as f64;
+
+            let c = counter.swap(0, Ordering::Relaxed);
+            let interval = 1_000_000.0 / elapsed;
+            println!(
+                "session,{},qos.background,{},{},{},{}",
+                scenario,
+                name,
+                background_payload,
+                c_background_priority,
+                (c as f64 / interval).floor() as usize
+            );
+        }
+    });
+
+    let sleep = Duration::from_secs_f64(opt.interval);
+    let mut count: u64 = 0;
+    loop {
+        let (priority_name, priority) = &priorities[count as usize % priorities.len()];
+        let message = make_message(count, opt.payload, *priority);
+
+        // Insert the pending ping
+        let (tx, rx) = mpsc::channel();
+        pending.lock().unwrap().insert(count, tx);
+        let now = Instant::now();
+        session.handle_message(message).unwrap();
+        // Wait for the pong to arrive
+        rx.recv().unwrap();
+        println!(
+            "session,{},latency.qos,{},{},{},{},{},{},{}",
+            opt.scenario,
+            opt.name,
+            opt.payload,
+            opt.interval,
+            priority_name,
+            opt.background_priority,
+            count,
+            now.elapsed().as_micros()
+        );
+
+        task::sleep(sleep).await;
+        count += 1;
+    }
+}
@@ -22,6 +22,7 @@ use zenoh::net::protocol::proto::ZenohMessage;
 use zenoh::net::protocol::session::{
     Session, SessionEventHandler, SessionHandler, SessionManager, SessionManagerConfig,
 };
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::core::ZResult;
 
 // Session Handler for the peer
@@ -71,6 +72,8 @@ struct Opt {
     locator: Locator,
     #[structopt(short = "m", long = "mode")]
     mode: String,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -84,6 +87,7 @@ async fn main() {
     let whatami = match opt.mode.as_str() {
         "peer" => whatami::PEER,
         "client" => whatami::CLIENT,
+        "router" => whatami::ROUTER,
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
 
@@ -92,16 +96,18 @@ async fn main() {
     rand::thread_rng().fill_bytes(&mut pid);
     let pid = PeerId::new(1, pid);
 
+    let opt_config = opt.tls.manager_config().await;
+
     let config = SessionManagerConfig {
         version: 0,
         whatami,
         id: pid,
         handler: Arc::new(MySH::new()),
     };
-    let manager = SessionManager::new(config, None);
+    let manager = SessionManager::new(config, opt_config);
 
     // Connect to the peer or listen
-    if whatami == whatami::PEER {
+    if whatami != whatami::CLIENT {
         manager.add_listener(&opt.locator).await.unwrap();
     } else {
         let _session = manager.open_session(&opt.locator).await.unwrap();
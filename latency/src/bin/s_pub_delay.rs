@@ -16,13 +16,16 @@ use async_std::task;
 use rand::RngCore;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use structopt::StructOpt;
-use zenoh::net::protocol::core::{whatami, Channel, PeerId, Priority, Reliability, ResKey};
+use zenoh::net::protocol::core::{whatami, Channel, PeerId, Priority, ResKey};
 use zenoh::net::protocol::link::Locator;
 use zenoh::net::protocol::proto::ZenohMessage;
 use zenoh::net::protocol::session::{
     DummySessionEventHandler, Session, SessionEventHandler, SessionHandler, SessionManager,
     SessionManagerConfig,
 };
+use zenoh_perf_common::reliability::reliability;
+use zenoh_perf_common::seq::header;
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::core::ZResult;
 
 struct MySH {}
@@ -53,6 +56,10 @@ struct Opt {
     payload: usize,
     #[structopt(short = "i", long = "interval")]
     interval: f64,
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -63,9 +70,12 @@ async fn main() {
     // Parse the args
     let opt = Opt::from_args();
 
+    let reliability = reliability(&opt.reliability);
+
     let whatami = match opt.mode.as_str() {
         "peer" => whatami::PEER,
         "client" => whatami::CLIENT,
+        "router" => whatami::ROUTER,
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
 
@@ -74,13 +84,15 @@ async fn main() {
     rand::thread_rng().fill_bytes(&mut pid);
     let pid = PeerId::new(1, pid);
 
+    let opt_config = opt.tls.manager_config().await;
+
     let config = SessionManagerConfig {
         version: 0,
         whatami,
         id: pid,
         handler: Arc::new(MySH::new()),
     };
-    let manager = SessionManager::new(config, None);
+    let manager = SessionManager::new(config, opt_config);
 
     // Connect to publisher
     let session = manager.open_session(&opt.locator).await.unwrap();
@@ -90,7 +102,7 @@ async fn main() {
         // Send reliable messages
         let channel = Channel {
             priority: Priority::Data,
-            reliability: Reliability::Reliable,
+            reliability,
         };
         let key = ResKey::RName("/test/ping".to_string());
         let info = None;
@@ -101,7 +113,7 @@ async fn main() {
         // u64 (8 bytes) for seq num
         // u128 (16 bytes) for system time in nanoseconds
         let mut payload = vec![0u8; opt.payload];
-        let count_bytes: [u8; 8] = count.to_le_bytes();
+        let count_bytes = header(count);
         let now_bytes: [u8; 16] = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
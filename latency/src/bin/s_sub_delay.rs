@@ -13,8 +13,10 @@
 //
 use async_std::future;
 use async_std::sync::Arc;
+use async_std::task;
 use rand::RngCore;
 use std::any::Any;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use structopt::StructOpt;
 use zenoh::net::protocol::core::{whatami, PeerId};
@@ -23,14 +25,32 @@ use zenoh::net::protocol::proto::{Data, ZenohBody, ZenohMessage};
 use zenoh::net::protocol::session::{
     Session, SessionEventHandler, SessionHandler, SessionManager, SessionManagerConfig,
 };
+use zenoh_perf_common::seq::{account, unstamp, SEQ_SIZE};
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::core::ZResult;
 
 // Session Handler for the peer
-struct MySH;
+struct MySH {
+    scenario: String,
+    name: String,
+    payload: usize,
+    counter: Arc<AtomicUsize>,
+    next: Arc<AtomicU64>,
+    lost: Arc<AtomicUsize>,
+    active: AtomicBool,
+}
 
 impl MySH {
-    fn new() -> Self {
-        Self
+    fn new(scenario: String, name: String, payload: usize) -> Self {
+        Self {
+            scenario,
+            name,
+            payload,
+            counter: Arc::new(AtomicUsize::new(0)),
+            next: Arc::new(AtomicU64::new(0)),
+            lost: Arc::new(AtomicUsize::new(0)),
+            active: AtomicBool::new(false),
+        }
     }
 }
 
@@ -39,16 +59,49 @@ impl SessionHandler for MySH {
         &self,
         _session: Session,
     ) -> ZResult<Arc<dyn SessionEventHandler + Send + Sync>> {
-        Ok(Arc::new(MyMH::new()))
+        if !self.active.swap(true, Ordering::Acquire) {
+            let count = self.counter.clone();
+            let lost = self.lost.clone();
+            let scenario = self.scenario.clone();
+            let name = self.name.clone();
+            let payload = self.payload;
+            task::spawn(async move {
+                loop {
+                    task::sleep(Duration::from_secs(1)).await;
+                    if count.swap(0, Ordering::Relaxed) > 0 {
+                        println!(
+                            "session,{},loss,{},{},{}",
+                            scenario,
+                            name,
+                            payload,
+                            lost.swap(0, Ordering::Relaxed)
+                        );
+                    }
+                }
+            });
+        }
+        Ok(Arc::new(MyMH::new(
+            self.counter.clone(),
+            self.next.clone(),
+            self.lost.clone(),
+        )))
     }
 }
 
 // Message Handler for the peer
-struct MyMH;
+struct MyMH {
+    counter: Arc<AtomicUsize>,
+    next: Arc<AtomicU64>,
+    lost: Arc<AtomicUsize>,
+}
 
 impl MyMH {
-    fn new() -> Self {
-        Self
+    fn new(counter: Arc<AtomicUsize>, next: Arc<AtomicU64>, lost: Arc<AtomicUsize>) -> Self {
+        Self {
+            counter,
+            next,
+            lost,
+        }
     }
 }
 
@@ -56,9 +109,12 @@ impl SessionEventHandler for MyMH {
     fn handle_message(&self, message: ZenohMessage) -> ZResult<()> {
         match message.body {
             ZenohBody::Data(Data { mut payload, .. }) => {
-                let mut count_bytes = [0u8; 8];
-                payload.read_bytes(&mut count_bytes);
-                let count = u64::from_le_bytes(count_bytes);
+                self.counter.fetch_add(1, Ordering::Relaxed);
+                // Account for the messages lost over a best-effort channel
+                let mut header = [0u8; SEQ_SIZE];
+                payload.read_bytes(&mut header);
+                let count = unstamp(header).unwrap_or_default();
+                account(&self.next, &self.lost, count);
 
                 let mut now_bytes = [0u8; 16];
                 payload.read_bytes(&mut now_bytes);
@@ -88,12 +144,20 @@ impl SessionEventHandler for MyMH {
 }
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "s_sub_thr")]
+#[structopt(name = "s_sub_delay")]
 struct Opt {
     #[structopt(short = "l", long = "locator")]
     locator: Locator,
     #[structopt(short = "m", long = "mode")]
     mode: String,
+    #[structopt(short = "n", long = "name")]
+    name: String,
+    #[structopt(short = "s", long = "scenario")]
+    scenario: String,
+    #[structopt(short = "p", long = "payload")]
+    payload: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -107,6 +171,7 @@ async fn main() {
     let whatami = match opt.mode.as_str() {
         "peer" => whatami::PEER,
         "client" => whatami::CLIENT,
+        "router" => whatami::ROUTER,
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
 
@@ -115,16 +180,18 @@ async fn main() {
     rand::thread_rng().fill_bytes(&mut pid);
     let pid = PeerId::new(1, pid);
 
+    let opt_config = opt.tls.manager_config().await;
+
     let config = SessionManagerConfig {
         version: 0,
         whatami,
         id: pid,
-        handler: Arc::new(MySH::new()),
+        handler: Arc::new(MySH::new(opt.scenario, opt.name, opt.payload)),
     };
-    let manager = SessionManager::new(config, None);
+    let manager = SessionManager::new(config, opt_config);
 
     // Connect to the peer or listen
-    if whatami == whatami::PEER {
+    if whatami != whatami::CLIENT {
         manager.add_listener(&opt.locator).await.unwrap();
     } else {
         let _session = manager.open_session(&opt.locator).await.unwrap();
@@ -19,6 +19,8 @@ use std::convert::TryInto;
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 use zenoh::*;
+use zenoh_perf_common::router;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "z_ping")]
@@ -37,10 +39,28 @@ struct Opt {
     interval: f64,
     #[structopt(long = "parallel")]
     parallel: bool,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
-async fn parallel(opt: Opt, config: Properties) {
+// Open zenoh, in client mode the time it takes includes the handshake with the
+// router since the open waits for the connection
+async fn open_zenoh(opt: &Opt, config: Properties) -> Zenoh {
+    let now = Instant::now();
     let zenoh = Zenoh::new(config.into()).await.unwrap();
+    if opt.mode == "client" {
+        println!(
+            "zenoh,{},handshake,{},{}",
+            opt.scenario,
+            opt.name,
+            now.elapsed().as_micros()
+        );
+    }
+    zenoh
+}
+
+async fn parallel(opt: Opt, config: Properties) {
+    let zenoh = open_zenoh(&opt, config).await;
     let zenoh = Arc::new(zenoh);
 
     // The hashmap with the pings
@@ -109,7 +129,7 @@ async fn parallel(opt: Opt, config: Properties) {
 }
 
 async fn single(opt: Opt, config: Properties) {
-    let zenoh = Zenoh::new(config.into()).await.unwrap();
+    let zenoh = open_zenoh(&opt, config).await;
 
     let scenario = opt.scenario;
     let name = opt.name;
@@ -168,6 +188,10 @@ async fn main() {
     let mut config = Properties::default();
     config.insert("mode".to_string(), opt.mode.clone());
 
+    opt.tls.configure(&mut config);
+
+    router::configure(&opt.mode, &mut config);
+
     if opt.locator.is_none() {
         config.insert("multicast_scouting".to_string(), "true".to_string());
     } else {
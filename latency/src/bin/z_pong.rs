@@ -16,6 +16,7 @@ use async_std::stream::StreamExt;
 use std::convert::TryInto;
 use structopt::StructOpt;
 use zenoh::*;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "z_pong")]
@@ -24,6 +25,8 @@ struct Opt {
     locator: String,
     #[structopt(short = "m", long = "mode")]
     mode: String,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -37,9 +40,11 @@ async fn main() {
     let mut config = Properties::default();
     config.insert("mode".to_string(), opt.mode.clone());
 
+    opt.tls.configure(&mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     match opt.mode.as_str() {
-        "peer" => config.insert("listener".to_string(), opt.locator),
+        "peer" | "router" => config.insert("listener".to_string(), opt.locator),
         "client" => config.insert("peer".to_string(), opt.locator),
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
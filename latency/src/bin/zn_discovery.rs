@@ -0,0 +1,235 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::sync::Arc;
+use async_std::task;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+use zenoh::net::info::{ZN_INFO_PEER_PID_KEY, ZN_INFO_ROUTER_PID_KEY};
+use zenoh::net::ResKey::*;
+use zenoh::net::*;
+use zenoh::Properties;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "zn_discovery")]
+struct Opt {
+    #[structopt(short = "n", long = "name")]
+    name: String,
+    #[structopt(short = "s", long = "scenario")]
+    scenario: String,
+    // The number of peers started on loopback, in this process
+    #[structopt(short = "e", long = "peers", default_value = "2")]
+    peers: usize,
+    // multicast, gossip: the multicast scouting is disabled and only the
+    // first peer is given to the others, which learn about each other from
+    // the link state it propagates, or router: the same with the router given
+    // to all the peers instead
+    #[structopt(long = "scouting", default_value = "multicast")]
+    scouting: String,
+    // The locator of the router of the router scouting, e.g. the zn_router
+    // discovery.sh spawns
+    #[structopt(long = "router", default_value = "tcp/127.0.0.1:7446")]
+    router: String,
+    // The peers listen on the consecutive TCP ports starting from this one
+    #[structopt(long = "port", default_value = "7447")]
+    port: u16,
+    #[structopt(long = "multicast-address")]
+    multicast_address: Option<String>,
+    #[structopt(long = "multicast-interface")]
+    multicast_interface: Option<String>,
+    // Seconds in between the checks of the connected peers and the writes
+    #[structopt(short = "i", long = "interval", default_value = "0.001")]
+    interval: f64,
+    // Seconds after which the peers still discovering are reported as such
+    #[structopt(long = "timeout", default_value = "10")]
+    timeout: f64,
+}
+
+fn config(opt: &Opt, index: usize) -> Properties {
+    let mut config = Properties::default();
+    config.insert("mode".to_string(), "peer".to_string());
+    config.insert(
+        "listener".to_string(),
+        format!("tcp/127.0.0.1:{}", opt.port as usize + index),
+    );
+    config.insert("peers_autoconnect".to_string(), "true".to_string());
+    match opt.scouting.as_str() {
+        "multicast" => {
+            config.insert("multicast_scouting".to_string(), "true".to_string());
+            if let Some(address) = opt.multicast_address.as_ref() {
+                config.insert("multicast_address".to_string(), address.clone());
+            }
+            if let Some(interface) = opt.multicast_interface.as_ref() {
+                config.insert("multicast_interface".to_string(), interface.clone());
+            }
+        }
+        "gossip" => {
+            config.insert("multicast_scouting".to_string(), "false".to_string());
+            config.insert("link_state".to_string(), "true".to_string());
+            if index > 0 {
+                config.insert("peer".to_string(), format!("tcp/127.0.0.1:{}", opt.port));
+            }
+        }
+        "router" => {
+            config.insert("multicast_scouting".to_string(), "false".to_string());
+            config.insert("link_state".to_string(), "true".to_string());
+            config.insert("peer".to_string(), opt.router.clone());
+        }
+        _ => panic!("Unsupported scouting: {}", opt.scouting),
+    }
+    config
+}
+
+// The number of peers the session is connected to, the router of the router
+// scouting is not one of them
+async fn connected(session: &Session) -> usize {
+    let info = session.info().await;
+    let pids = |key: ZInt| -> HashSet<String> {
+        info.get(&key)
+            .map(|pids| {
+                pids.split(',')
+                    .filter(|pid| !pid.is_empty())
+                    .map(|pid| pid.to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let routers = pids(ZN_INFO_ROUTER_PID_KEY);
+    pids(ZN_INFO_PEER_PID_KEY).difference(&routers).count()
+}
+
+// Run a peer until every peer has discovered the others and received data, or
+// until the timeout. It keeps writing once done for the late peers to receive
+// data, the session is returned to stay open until all the peers are done.
+async fn peer(opt: Arc<Opt>, index: usize, start: Instant, pending: Arc<AtomicUsize>) -> Session {
+    let session = open(config(&opt, index).into()).await.unwrap();
+
+    // The time the first data from another peer is received at, and the
+    // peers data was received from
+    let own = format!("/test/discovery/{}", index);
+    let first_data = Arc::new(Mutex::new(None));
+    let c_first_data = first_data.clone();
+    let senders = Arc::new(Mutex::new(HashSet::new()));
+    let c_senders = senders.clone();
+    let c_own = own.clone();
+    let sub_info = SubInfo {
+        reliability: Reliability::Reliable,
+        mode: SubMode::Push,
+        period: None,
+    };
+    let _sub = session
+        .declare_callback_subscriber(
+            &RName("/test/discovery/*".to_string()),
+            &sub_info,
+            move |sample| {
+                if sample.res_name != c_own {
+                    let mut guard = c_first_data.lock().unwrap();
+                    if guard.is_none() {
+                        *guard = Some(start.elapsed());
+                    }
+                    c_senders.lock().unwrap().insert(sample.res_name);
+                }
+            },
+        )
+        .await
+        .unwrap();
+
+    let reskey = RId(session.declare_resource(&RName(own)).await.unwrap());
+    let _publ = session.declare_publisher(&reskey).await.unwrap();
+
+    let sleep = Duration::from_secs_f64(opt.interval);
+    let timeout = Duration::from_secs_f64(opt.timeout);
+    let data: ZBuf = (index as u64).to_le_bytes().to_vec().into();
+    let mut discovered = None;
+    let mut done = false;
+    while pending.load(Ordering::Relaxed) > 0 && start.elapsed() < timeout {
+        // The peers of the router scouting may only reach each other through
+        // the router, they are discovered once their data is received
+        let others = match opt.scouting.as_str() {
+            "router" => senders.lock().unwrap().len(),
+            _ => connected(&session).await,
+        };
+        if discovered.is_none() && others + 1 >= opt.peers {
+            discovered = Some(start.elapsed());
+        }
+        session.write(&reskey, data.clone()).await.unwrap();
+
+        if !done && discovered.is_some() && first_data.lock().unwrap().is_some() {
+            done = true;
+            pending.fetch_sub(1, Ordering::Relaxed);
+        }
+        task::sleep(sleep).await;
+    }
+
+    match discovered {
+        Some(elapsed) => println!(
+            "zenoh-net,{},discovery.{},{},{},{},{}",
+            opt.scenario,
+            opt.scouting,
+            opt.name,
+            opt.peers,
+            index,
+            elapsed.as_micros()
+        ),
+        None => println!(
+            "zenoh-net,{},discovery.{}.timeout,{},{},{}",
+            opt.scenario, opt.scouting, opt.name, opt.peers, index
+        ),
+    }
+    match *first_data.lock().unwrap() {
+        Some(elapsed) => println!(
+            "zenoh-net,{},discovery.{}.data,{},{},{},{}",
+            opt.scenario,
+            opt.scouting,
+            opt.name,
+            opt.peers,
+            index,
+            elapsed.as_micros()
+        ),
+        None => println!(
+            "zenoh-net,{},discovery.{}.data.timeout,{},{},{}",
+            opt.scenario, opt.scouting, opt.name, opt.peers, index
+        ),
+    }
+    session
+}
+
+#[async_std::main]
+async fn main() {
+    // initiate logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    if opt.peers < 2 {
+        panic!("Unsupported peers: {}", opt.peers);
+    }
+
+    // The times are measured from the start of all the peers at once
+    let opt = Arc::new(opt);
+    let start = Instant::now();
+    let pending = Arc::new(AtomicUsize::new(opt.peers));
+    let handles = (0..opt.peers)
+        .map(|index| task::spawn(peer(opt.clone(), index, start, pending.clone())))
+        .collect::<Vec<_>>();
+
+    // Keep the sessions of the peers done first open until all are done
+    let mut sessions = Vec::with_capacity(handles.len());
+    for handle in handles {
+        sessions.push(handle.await);
+    }
+}
@@ -11,6 +11,8 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
+use async_std::channel;
+use async_std::future;
 use async_std::task;
 use std::collections::HashMap;
 use std::sync::{Arc, Barrier, Mutex};
@@ -19,6 +21,10 @@ use structopt::StructOpt;
 use zenoh::net::ResKey::*;
 use zenoh::net::*;
 use zenoh::Properties;
+use zenoh_perf_common::memory::resident_memory;
+use zenoh_perf_common::reliability::{congestion_control, lossy, reliability};
+use zenoh_perf_common::router;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "zn_ping")]
@@ -39,10 +45,37 @@ struct Opt {
     parallel: bool,
     #[structopt(short = "d", long = "samples")]
     samples: Option<usize>,
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(long = "timeout", default_value = "1")]
+    timeout: f64,
+    #[structopt(long = "congestion", default_value = "block")]
+    congestion: String,
+    // Print the resident memory of the whole process every second
+    #[structopt(long = "memory")]
+    memory: bool,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
-async fn single(opt: Opt, config: Properties) {
+// Open the session, in client mode the time it takes includes the handshake
+// with the router since the open waits for the connection
+async fn open_session(opt: &Opt, config: Properties) -> Session {
+    let now = Instant::now();
     let session = open(config.into()).await.unwrap();
+    if opt.mode == "client" {
+        println!(
+            "zenoh-net,{},handshake,{},{}",
+            opt.scenario,
+            opt.name,
+            now.elapsed().as_micros()
+        );
+    }
+    session
+}
+
+async fn single(opt: Opt, config: Properties, reliability: Reliability) {
+    let session = open_session(&opt, config).await;
 
     // The resource to wait the response back
     let reskey_pong = RId(session
@@ -50,7 +83,7 @@ async fn single(opt: Opt, config: Properties) {
         .await
         .unwrap());
     let sub_info = SubInfo {
-        reliability: Reliability::Reliable,
+        reliability,
         mode: SubMode::Push,
         period: None,
     };
@@ -67,6 +100,9 @@ async fn single(opt: Opt, config: Properties) {
     let _publ = session.declare_publisher(&reskey_ping).await.unwrap();
 
     let sleep = Duration::from_secs_f64(opt.interval);
+    let timeout = Duration::from_secs_f64(opt.timeout);
+    let congestion_control = congestion_control(&opt.congestion);
+    let lossy = lossy(reliability, congestion_control);
     let payload = vec![0u8; opt.payload - 8];
     let mut count: u64 = 0;
     loop {
@@ -77,21 +113,68 @@ async fn single(opt: Opt, config: Properties) {
         let data: ZBuf = data.into();
 
         let now = Instant::now();
-        session
+        let res = session
             .write_ext(
                 &reskey_ping,
                 data,
                 encoding::DEFAULT,
                 data_kind::DEFAULT,
-                CongestionControl::Block, // Make sure to not drop messages because of congestion control
+                congestion_control,
             )
-            .wait()
-            .unwrap();
+            .wait();
+        // A write that returns early with an error is reported as dropped by
+        // the ping, the pings dropped further on are reported as lost when
+        // their pong does not arrive
+        if res.is_err() {
+            println!(
+                "zenoh-net,{},latency.sequential.dropped,{},{},{},{}",
+                opt.scenario,
+                opt.name,
+                payload.len(),
+                opt.interval,
+                count
+            );
+            task::sleep(sleep).await;
+            count += 1;
+            continue;
+        }
 
-        let mut sample = sub.receiver().recv().unwrap();
-        let mut count_bytes = [0u8; 8];
-        sample.payload.read_bytes(&mut count_bytes);
-        let s_count = u64::from_le_bytes(count_bytes);
+        // Wait for the pong to arrive, pings that may be lost are accounted
+        // as such if the pong does not arrive before the timeout
+        let mut pong = None;
+        while pong.is_none() {
+            let mut sample = if lossy {
+                match sub.receiver().recv_timeout(timeout) {
+                    Ok(sample) => sample,
+                    Err(_) => break,
+                }
+            } else {
+                sub.receiver().recv().unwrap()
+            };
+            let mut count_bytes = [0u8; 8];
+            sample.payload.read_bytes(&mut count_bytes);
+            let s_count = u64::from_le_bytes(count_bytes);
+            // Discard the late pongs of the pings already accounted as lost
+            if s_count == count {
+                pong = Some((sample, s_count));
+            }
+        }
+        let (sample, s_count) = match pong {
+            Some(pong) => pong,
+            None => {
+                println!(
+                    "zenoh-net,{},latency.sequential.lost,{},{},{},{}",
+                    opt.scenario,
+                    opt.name,
+                    payload.len(),
+                    opt.interval,
+                    count
+                );
+                task::sleep(sleep).await;
+                count += 1;
+                continue;
+            }
+        };
         println!(
             "zenoh-net,{},latency.sequential,{},{},{},{},{}",
             opt.scenario,
@@ -107,8 +190,8 @@ async fn single(opt: Opt, config: Properties) {
     }
 }
 
-async fn parallel(opt: Opt, config: Properties) {
-    let session = open(config.into()).await.unwrap();
+async fn parallel(opt: Opt, config: Properties, reliability: Reliability) {
+    let session = open_session(&opt, config).await;
     let session = Arc::new(session);
 
     // The hashmap with the pings
@@ -118,8 +201,8 @@ async fn parallel(opt: Opt, config: Properties) {
     let c_pending = pending.clone();
     let c_barrier = barrier.clone();
     let c_session = session.clone();
-    let scenario = opt.scenario;
-    let name = opt.name;
+    let scenario = opt.scenario.clone();
+    let name = opt.name.clone();
     let interval = opt.interval;
     task::spawn(async move {
         // The resource to wait the response back
@@ -129,7 +212,7 @@ async fn parallel(opt: Opt, config: Properties) {
             .unwrap());
 
         let sub_info = SubInfo {
-            reliability: Reliability::Reliable,
+            reliability,
             mode: SubMode::Push,
             period: None,
         };
@@ -145,7 +228,12 @@ async fn parallel(opt: Opt, config: Properties) {
             let mut count_bytes = [0u8; 8];
             sample.payload.read_bytes(&mut count_bytes);
             let count = u64::from_le_bytes(count_bytes);
-            let instant = c_pending.lock().unwrap().remove(&count).unwrap();
+            let mut guard = c_pending.lock().unwrap();
+            // A late pong whose ping has already been accounted as lost
+            let instant = match guard.remove(&count) {
+                Some(instant) => instant,
+                None => continue,
+            };
             println!(
                 "zenoh-net,{},latency.parallel,{},{},{},{},{}",
                 scenario,
@@ -155,6 +243,20 @@ async fn parallel(opt: Opt, config: Properties) {
                 count,
                 instant.elapsed().as_micros()
             );
+            // Pongs are received in order, any older pending ping has been lost
+            let mut lost: Vec<u64> = guard.keys().filter(|c| **c < count).copied().collect();
+            lost.sort_unstable();
+            for c in lost {
+                guard.remove(&c);
+                println!(
+                    "zenoh-net,{},latency.parallel.lost,{},{},{},{}",
+                    scenario,
+                    name,
+                    sample.payload.len(),
+                    interval,
+                    c
+                );
+            }
         }
     });
 
@@ -168,6 +270,7 @@ async fn parallel(opt: Opt, config: Properties) {
     // Wait for the both publishers and subscribers to be declared
     barrier.wait();
 
+    let congestion_control = congestion_control(&opt.congestion);
     let sleep = Duration::from_secs_f64(opt.interval);
     let payload = vec![0u8; opt.payload - 8];
     let mut count: u64 = 0;
@@ -180,24 +283,37 @@ async fn parallel(opt: Opt, config: Properties) {
         let data: ZBuf = data.into();
 
         pending.lock().unwrap().insert(count, Instant::now());
-        session
+        let res = session
             .write_ext(
                 &reskey_ping,
                 data,
                 encoding::DEFAULT,
                 data_kind::DEFAULT,
-                CongestionControl::Block, // Make sure to not drop messages because of congestion control
+                congestion_control,
             )
-            .wait()
-            .unwrap();
+            .wait();
+        // A write that returns early with an error is reported as dropped by
+        // the ping, the pings dropped further on are reported as lost when
+        // their pong does not arrive
+        if res.is_err() {
+            pending.lock().unwrap().remove(&count);
+            println!(
+                "zenoh-net,{},latency.parallel.dropped,{},{},{},{}",
+                opt.scenario,
+                opt.name,
+                payload.len(),
+                opt.interval,
+                count
+            );
+        }
 
         task::sleep(sleep).await;
         count += 1;
     }
 }
 
-async fn samples(opt: Opt, config: Properties) {
-    let session = open(config.into()).await.unwrap();
+async fn samples(opt: Opt, config: Properties, reliability: Reliability) {
+    let session = open_session(&opt, config).await;
 
     // The resource to wait the response back
     let reskey_pong = RId(session
@@ -205,16 +321,19 @@ async fn samples(opt: Opt, config: Properties) {
         .await
         .unwrap());
     let sub_info = SubInfo {
-        reliability: Reliability::Reliable,
+        reliability,
         mode: SubMode::Push,
         period: None,
     };
 
-    let barrier = Arc::new(Barrier::new(2));
-    let c_barrier = barrier.clone();
+    // The counts of the pongs, a best-effort pong that does not arrive before
+    // the timeout is accounted as lost
+    let (tx, rx) = channel::unbounded::<u64>();
     let _sub = session
-        .declare_callback_subscriber(&reskey_pong, &sub_info, move |_sample| {
-            c_barrier.wait();
+        .declare_callback_subscriber(&reskey_pong, &sub_info, move |mut sample| {
+            let mut count_bytes = [0u8; 8];
+            sample.payload.read_bytes(&mut count_bytes);
+            let _ = tx.try_send(u64::from_le_bytes(count_bytes));
         })
         .await
         .unwrap();
@@ -226,17 +345,25 @@ async fn samples(opt: Opt, config: Properties) {
         .unwrap());
     let _publ = session.declare_publisher(&reskey_ping).await.unwrap();
 
-    let mut samples = vec![0u128; opt.samples.unwrap()];
+    let num = opt.samples.unwrap();
+    let mut samples: Vec<Option<u128>> = vec![None; num];
 
     let sleep = Duration::from_secs_f64(opt.interval);
-    let data: ZBuf = vec![0u8; opt.payload].into();
+    let timeout = Duration::from_secs_f64(opt.timeout);
+    let lossy = reliability == Reliability::BestEffort;
+    let payload = vec![0u8; opt.payload - 8];
+
+    for (count, sample) in (0u64..).zip(samples.iter_mut()) {
+        let mut data: WBuf = WBuf::new(opt.payload, true);
+        data.write_bytes(&count.to_le_bytes());
+        data.write_bytes(&payload);
+        let data: ZBuf = data.into();
 
-    for i in 0..opt.samples.unwrap() {
         let now = Instant::now();
         session
             .write_ext(
                 &reskey_ping,
-                data.clone(),
+                data,
                 encoding::DEFAULT,
                 data_kind::DEFAULT,
                 CongestionControl::Block, // Make sure to not drop messages because of congestion control
@@ -244,17 +371,37 @@ async fn samples(opt: Opt, config: Properties) {
             .wait()
             .unwrap();
 
-        barrier.wait();
-        samples[i] = now.elapsed().as_micros();
+        // Wait for the pong to arrive, a best-effort one may never do
+        loop {
+            let pong = if lossy {
+                future::timeout(timeout, rx.recv()).await.ok()
+            } else {
+                Some(rx.recv().await)
+            };
+            match pong {
+                Some(Ok(s_count)) if s_count == count => {
+                    *sample = Some(now.elapsed().as_micros());
+                    break;
+                }
+                // A late pong of a ping already accounted as lost
+                Some(Ok(_)) => {}
+                _ => break,
+            }
+        }
         task::sleep(sleep).await;
     }
 
-    let num = opt.samples.unwrap();
-    for (i, s) in samples.iter().enumerate().take(num) {
-        println!(
-            "zenoh-net,{},latency.sequential.samples,{},{},{},{},{}",
-            opt.scenario, opt.name, opt.payload, opt.interval, i, s
-        );
+    for (i, s) in samples.iter().enumerate() {
+        match s {
+            Some(s) => println!(
+                "zenoh-net,{},latency.sequential.samples,{},{},{},{},{}",
+                opt.scenario, opt.name, opt.payload, opt.interval, i, s
+            ),
+            None => println!(
+                "zenoh-net,{},latency.sequential.samples.lost,{},{},{},{}",
+                opt.scenario, opt.name, opt.payload, opt.interval, i
+            ),
+        }
     }
 }
 
@@ -266,20 +413,55 @@ async fn main() {
     // Parse the args
     let opt = Opt::from_args();
 
+    // The count is written on the first 8 bytes
+    if opt.payload < 8 {
+        panic!("Unsupported payload: {}", opt.payload);
+    }
+
     let mut config = Properties::default();
     config.insert("mode".to_string(), opt.mode.clone());
 
+    opt.tls.configure(&mut config);
+
+    router::configure(&opt.mode, &mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     config.insert("peer".to_string(), opt.locator.clone());
 
+    let reliability = reliability(&opt.reliability);
+
+    if opt.memory {
+        let scenario = opt.scenario.clone();
+        let name = opt.name.clone();
+        let payload = opt.payload;
+        task::spawn(async move {
+            loop {
+                task::sleep(Duration::from_secs(1)).await;
+                println!(
+                    "zenoh-net,{},memory,{},{},{}",
+                    scenario,
+                    name,
+                    payload,
+                    resident_memory()
+                );
+            }
+        });
+    }
+
     if opt.parallel {
-        parallel(opt, config).await;
+        parallel(opt, config, reliability).await;
         return;
     }
     if opt.samples.is_some() {
-        samples(opt, config).await;
+        if opt.congestion != "block" {
+            panic!(
+                "Congestion control {} is not supported with samples",
+                opt.congestion
+            );
+        }
+        samples(opt, config, reliability).await;
         return;
     }
 
-    single(opt, config).await;
+    single(opt, config, reliability).await;
 }
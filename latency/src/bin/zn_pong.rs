@@ -12,10 +12,14 @@
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
 use async_std::future;
+use async_std::task;
 use structopt::StructOpt;
 use zenoh::net::ResKey::*;
 use zenoh::net::*;
 use zenoh::Properties;
+use zenoh_perf_common::reliability::{congestion_control, reliability};
+use zenoh_perf_common::sub::SubOpt;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "zn_pong")]
@@ -24,6 +28,14 @@ struct Opt {
     locator: String,
     #[structopt(short = "m", long = "mode")]
     mode: String,
+    #[structopt(flatten)]
+    sub: SubOpt,
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(long = "congestion", default_value = "block")]
+    congestion: String,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -37,9 +49,11 @@ async fn main() {
     let mut config = Properties::default();
     config.insert("mode".to_string(), opt.mode.clone());
 
+    opt.tls.configure(&mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     match opt.mode.as_str() {
-        "peer" => config.insert("listener".to_string(), opt.locator),
+        "peer" | "router" => config.insert("listener".to_string(), opt.locator),
         "client" => config.insert("peer".to_string(), opt.locator),
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
@@ -58,27 +72,57 @@ async fn main() {
         .declare_resource(&RName("/test/ping".to_string()))
         .await
         .unwrap());
+    let mode = opt.sub.mode();
+    let period = opt.sub.period();
+    let reliability = reliability(&opt.reliability);
+    let congestion_control = congestion_control(&opt.congestion);
     let sub_info = SubInfo {
-        reliability: Reliability::Reliable,
-        mode: SubMode::Push,
-        period: None,
+        reliability,
+        mode,
+        period,
     };
 
     let mut sub = session
         .declare_subscriber(&reskey_ping, &sub_info)
         .await
         .unwrap();
+    if sub_info.mode == SubMode::Pull {
+        // Pull the available pings at the given cadence and echo back at most
+        // --max-samples of them, the others wait for the next pull
+        let sleep = opt.sub.pull_interval();
+        let samples = opt.sub.samples_per_pull();
+        loop {
+            sub.pull().await.unwrap();
+            task::sleep(sleep).await;
+            for _ in 0..samples {
+                let sample = match sub.receiver().try_recv() {
+                    Ok(sample) => sample,
+                    Err(_) => break,
+                };
+                // A pong dropped on congestion is accounted as lost by the ping
+                let _ = session
+                    .write_ext(
+                        &reskey_pong,
+                        sample.payload,
+                        encoding::DEFAULT,
+                        data_kind::DEFAULT,
+                        congestion_control,
+                    )
+                    .wait();
+            }
+        }
+    }
     while let Ok(sample) = sub.receiver().recv() {
-        session
+        // A pong dropped on congestion is accounted as lost by the ping
+        let _ = session
             .write_ext(
                 &reskey_pong,
                 sample.payload,
                 encoding::DEFAULT,
                 data_kind::DEFAULT,
-                CongestionControl::Block, // Make sure to not drop messages because of congestion control
+                congestion_control,
             )
-            .wait()
-            .unwrap();
+            .wait();
     }
 
     // Stop forever
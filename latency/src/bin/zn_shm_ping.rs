@@ -0,0 +1,145 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::task;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+use zenoh::net::ResKey::*;
+use zenoh::net::*;
+use zenoh::Properties;
+use zenoh_perf_common::router;
+use zenoh_perf_tls::TlsOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "zn_shm_ping")]
+struct Opt {
+    #[structopt(short = "l", long = "locator")]
+    locator: String,
+    #[structopt(short = "m", long = "mode")]
+    mode: String,
+    #[structopt(short = "p", long = "payload")]
+    payload: usize,
+    #[structopt(short = "n", long = "name")]
+    name: String,
+    #[structopt(short = "s", long = "scenario")]
+    scenario: String,
+    #[structopt(short = "i", long = "interval")]
+    interval: f64,
+    // The number of payloads the shared memory segment can hold at once, the
+    // buffers are released once the pongs are received
+    #[structopt(long = "buffers", default_value = "4")]
+    buffers: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
+}
+
+// Allocate a buffer in the shared memory segment, reclaiming the buffers
+// released by the pong when the segment is full
+async fn alloc(shm: &mut SharedMemoryManager, size: usize) -> SharedMemoryBuf {
+    loop {
+        if let Some(sbuf) = shm.alloc(size) {
+            return sbuf;
+        }
+        if shm.garbage_collect() > 0 {
+            shm.defragment();
+        } else {
+            task::yield_now().await;
+        }
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // initiate logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    // The count is written on the first 8 bytes
+    if opt.payload < 8 {
+        panic!("Unsupported payload: {}", opt.payload);
+    }
+    if opt.buffers == 0 {
+        panic!("Unsupported buffers: {}", opt.buffers);
+    }
+
+    let mut config = Properties::default();
+    config.insert("mode".to_string(), opt.mode.clone());
+
+    opt.tls.configure(&mut config);
+
+    router::configure(&opt.mode, &mut config);
+
+    config.insert("multicast_scouting".to_string(), "false".to_string());
+    config.insert("peer".to_string(), opt.locator.clone());
+
+    let session = open(config.into()).await.unwrap();
+
+    // The segment is named after the session, the pong on the same host maps
+    // it and echoes the pings back without copying them
+    let id = session.id().await;
+    let mut shm = SharedMemoryManager::new(id, opt.payload * opt.buffers).unwrap();
+
+    // The resource to wait the response back
+    let reskey_pong = RId(session
+        .declare_resource(&RName("/test/pong".to_string()))
+        .await
+        .unwrap());
+    let sub_info = SubInfo {
+        reliability: Reliability::Reliable,
+        mode: SubMode::Push,
+        period: None,
+    };
+    let mut sub = session
+        .declare_subscriber(&reskey_pong, &sub_info)
+        .await
+        .unwrap();
+
+    // The resource to publish data on
+    let reskey_ping = RId(session
+        .declare_resource(&RName("/test/ping".to_string()))
+        .await
+        .unwrap());
+    let _publ = session.declare_publisher(&reskey_ping).await.unwrap();
+
+    let sleep = Duration::from_secs_f64(opt.interval);
+    let mut count: u64 = 0;
+    loop {
+        let now = Instant::now();
+        // The ping is written in place in the shared memory, the allocation
+        // is part of the measured latency as the copy is for zn_ping
+        let mut sbuf = alloc(&mut shm, opt.payload).await;
+        let slice = unsafe { sbuf.as_mut_slice() };
+        slice[..8].copy_from_slice(&count.to_le_bytes());
+
+        session.write(&reskey_ping, sbuf.into()).wait().unwrap();
+
+        let mut sample = sub.receiver().recv().unwrap();
+        let mut count_bytes = [0u8; 8];
+        sample.payload.read_bytes(&mut count_bytes);
+        let s_count = u64::from_le_bytes(count_bytes);
+        println!(
+            "zenoh-net-shm,{},latency.sequential,{},{},{},{},{}",
+            opt.scenario,
+            opt.name,
+            sample.payload.len(),
+            opt.interval,
+            s_count,
+            now.elapsed().as_micros()
+        );
+
+        task::sleep(sleep).await;
+        count += 1;
+    }
+}
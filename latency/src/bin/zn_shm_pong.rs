@@ -0,0 +1,84 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::future;
+use structopt::StructOpt;
+use zenoh::net::ResKey::*;
+use zenoh::net::*;
+use zenoh::Properties;
+use zenoh_perf_tls::TlsOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "zn_shm_pong")]
+struct Opt {
+    #[structopt(short = "l", long = "locator")]
+    locator: String,
+    #[structopt(short = "m", long = "mode")]
+    mode: String,
+    #[structopt(flatten)]
+    tls: TlsOpt,
+}
+
+#[async_std::main]
+async fn main() {
+    // initiate logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    let mut config = Properties::default();
+    config.insert("mode".to_string(), opt.mode.clone());
+
+    opt.tls.configure(&mut config);
+
+    config.insert("multicast_scouting".to_string(), "false".to_string());
+    match opt.mode.as_str() {
+        "peer" | "router" => config.insert("listener".to_string(), opt.locator),
+        "client" => config.insert("peer".to_string(), opt.locator),
+        _ => panic!("Unsupported mode: {}", opt.mode),
+    };
+
+    let session = open(config.into()).await.unwrap();
+
+    // The resource to echo the data back
+    let reskey_pong = RId(session
+        .declare_resource(&RName("/test/pong".to_string()))
+        .await
+        .unwrap());
+    let _publ = session.declare_publisher(&reskey_pong).await.unwrap();
+
+    // The resource to read the data from
+    let reskey_ping = RId(session
+        .declare_resource(&RName("/test/ping".to_string()))
+        .await
+        .unwrap());
+
+    let sub_info = SubInfo {
+        reliability: Reliability::Reliable,
+        mode: SubMode::Push,
+        period: None,
+    };
+    let mut sub = session
+        .declare_subscriber(&reskey_ping, &sub_info)
+        .await
+        .unwrap();
+    // The pings still refer to the shared memory segment of zn_shm_ping, they
+    // are echoed back as is and only their descriptor crosses the link
+    while let Ok(sample) = sub.receiver().recv() {
+        session.write(&reskey_pong, sample.payload).wait().unwrap();
+    }
+
+    // Stop forever
+    future::pending::<()>().await;
+}
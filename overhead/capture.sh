@@ -1,3 +1,49 @@
 #!/usr/bin/env bash
+#
+# Record the zenoh traffic on port 7447 in zenoh_overhead.pcap for analyze.sh,
+# which requires root:
+#   ./capture.sh
+# Or report the overhead of zn_overhead without any capture: it connects to
+# zn_overhead_proxy on port 7448, which forwards to port 7447 and prints the
+# report once the connection is closed:
+#   ./capture.sh --proxy PAYLOAD [ZN_OVERHEAD_ARGS...]
+#
+# With ROUTER=1 the zn_router of the throughput crate is spawned on port 7447
+# for the capture to record the traffic of the clients connecting to it, and
+# zn_overhead connects to it in client mode through the proxy.
+ROUTER=${ROUTER:-0}
 
-sudo tcpdump -i lo 'port 7447' -w zenoh_overhead.pcap
\ No newline at end of file
+. ../router.sh
+
+# The router is stopped on exit, including when the capture is interrupted
+if [ "$ROUTER" = 1 ]; then
+    start_router tcp/127.0.0.1:7447
+    trap stop_router EXIT
+fi
+
+if [ "$1" != "--proxy" ]; then
+    sudo tcpdump -i lo 'port 7447' -w zenoh_overhead.pcap
+    exit
+fi
+
+PAYLOAD=${2:?"Usage: $0 --proxy PAYLOAD [ZN_OVERHEAD_ARGS...]"}
+shift 2
+
+MODE=peer
+if [ "$ROUTER" = 1 ]; then
+    MODE=client
+fi
+
+../target/release/zn_overhead_proxy -l 127.0.0.1:7448 -p 127.0.0.1:7447 &
+PROXY=$!
+sleep 1
+
+../target/release/zn_overhead -m $MODE -l tcp/127.0.0.1:7448 -p $PAYLOAD "$@"
+
+# Leave the proxy the time to print the report
+sleep 1
+kill $PROXY
+
+if [ "$ROUTER" = 1 ]; then
+    print_router_stats overhead,$PAYLOAD
+fi
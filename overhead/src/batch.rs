@@ -0,0 +1,82 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use zenoh::net::protocol::io::ZBuf;
+use zenoh::net::protocol::proto::SessionMessage;
+
+// The batches of a stream link, each prefixed by its length on 16 bits,
+// split as the bytes are received
+#[derive(Default)]
+pub struct Batches {
+    pending: Vec<u8>,
+    // The start of the next batch in the pending bytes
+    offset: usize,
+}
+
+impl Batches {
+    // Append the bytes received, dropping the batches already read
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.pending.drain(..self.offset);
+        self.offset = 0;
+        self.pending.extend_from_slice(bytes);
+    }
+
+    // The next batch received entirely, if any
+    pub fn next_batch(&mut self) -> Option<&[u8]> {
+        let rest = &self.pending[self.offset..];
+        if rest.len() < 2 {
+            return None;
+        }
+        let start = self.offset + 2;
+        let end = start + u16::from_le_bytes([rest[0], rest[1]]) as usize;
+        if end > self.pending.len() {
+            return None;
+        }
+        self.offset = end;
+        Some(&self.pending[start..end])
+    }
+}
+
+// Decode the session messages of a batch, up to the first that cannot be
+pub fn session_messages(batch: &[u8]) -> Vec<SessionMessage> {
+    let mut zbuf = ZBuf::from(batch.to_vec());
+    let mut messages = vec![];
+    while zbuf.can_read() {
+        match zbuf.read_session_message() {
+            Some(msg) => messages.push(msg),
+            None => break,
+        }
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches() {
+        let mut batches = Batches::default();
+        batches.extend(&[3, 0, 1, 2]);
+        assert_eq!(batches.next_batch(), None);
+        batches.extend(&[3, 0, 0, 1]);
+        assert_eq!(batches.next_batch(), Some(&[1u8, 2, 3][..]));
+        assert_eq!(batches.next_batch(), Some(&[][..]));
+        assert_eq!(batches.next_batch(), None);
+        batches.extend(&[0]);
+        assert_eq!(batches.next_batch(), None);
+        batches.extend(&[4, 5]);
+        assert_eq!(batches.next_batch(), Some(&[4u8][..]));
+        assert_eq!(batches.next_batch(), None);
+    }
+}
@@ -0,0 +1,256 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+
+extern crate serde;
+use structopt::StructOpt;
+//use zenoh::net::ResKey::*;
+use async_std::fs;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+use zenoh::net::protocol::io::ZBuf;
+use zenoh::net::protocol::proto::SessionMessage;
+
+mod pcap;
+mod reassembly;
+
+use pcap::PcapReader;
+use reassembly::{Flow, Flows};
+use zenoh_perf_overhead::report::Stats;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "zn_analyze")]
+struct Opt {
+    #[structopt(short = "j", long = "json")]
+    json: Option<String>,
+    #[structopt(short = "p", long = "pcap")]
+    pcap: Option<String>,
+    #[structopt(long = "port", default_value = "7447")]
+    port: u16,
+    #[structopt(short = "c", long = "csv")]
+    csv: bool,
+    #[structopt(short = "b", long = "batch-size", default_value = "65535")]
+    batch_size: usize,
+    #[structopt(short = "i", long = "interval", default_value = "1")]
+    interval: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PcapData {
+    #[serde(alias = "_index")]
+    pub index: String,
+    #[serde(alias = "_type")]
+    pub pcap_type: String,
+    #[serde(alias = "_score")]
+    pub score: Option<String>,
+    #[serde(alias = "_source")]
+    pub source: Layers,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Layers {
+    #[serde(alias = "_layers")]
+    pub layers: PcapLayers,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PcapLayers {
+    #[serde(alias = "frame.time_epoch")]
+    pub frame_time: Option<Vec<String>>,
+    #[serde(alias = "frame.len")]
+    pub frame_len: Option<Vec<String>>,
+    #[serde(alias = "ip.len")]
+    pub ip_len: Option<Vec<String>>,
+    #[serde(alias = "tcp.dstport")]
+    pub tcp_dest: Option<Vec<String>>,
+    #[serde(alias = "tcp.srcport")]
+    pub tcp_src: Option<Vec<String>>,
+    #[serde(alias = "tcp.payload")]
+    pub tcp_payload: Option<Vec<String>>,
+    #[serde(alias = "udp.dstport")]
+    pub udp_dest: Option<Vec<String>>,
+    #[serde(alias = "udp.srcport")]
+    pub udp_src: Option<Vec<String>>,
+    #[serde(alias = "udp.payload")]
+    pub udp_payload: Option<Vec<String>>,
+}
+
+// Decode all the session messages in a buffer, starting at the given offset
+fn decode_session_messages(
+    mut zbuf: ZBuf,
+    offset: usize,
+    messages: &mut Vec<(usize, SessionMessage)>,
+) {
+    while zbuf.can_read() {
+        match zbuf.read_session_message() {
+            Some(msg) => messages.push((offset, msg)),
+            None => break,
+        }
+    }
+}
+
+// Read the session messages of a TCP stream, prefixed by their length, with
+// the offset of their batch in the stream
+fn read_session_messages(mut data: &[u8]) -> Vec<(usize, SessionMessage)> {
+    let mut messages = Vec::with_capacity(1);
+    let mut offset = 0;
+    let mut length_bytes = [0u8; 2];
+    while data.read_exact(&mut length_bytes).is_ok() {
+        let to_read = u16::from_le_bytes(length_bytes) as usize;
+        // Read the message, the stream may be truncated by the end of the
+        // capture or by a gap
+        let mut buffer = vec![0u8; to_read];
+        if data.read_exact(&mut buffer).is_err() {
+            break;
+        }
+
+        decode_session_messages(ZBuf::from(buffer), offset, &mut messages);
+        offset += 2 + to_read;
+    }
+
+    messages
+}
+
+// Read the session messages of a UDP datagram, which are not framed
+fn read_datagram_messages(data: &[u8]) -> Vec<(usize, SessionMessage)> {
+    let mut messages = vec![];
+    decode_session_messages(ZBuf::from(data.to_vec()), 0, &mut messages);
+    messages
+}
+
+// Parse the first value of a field exported by tshark
+fn first<T: FromStr>(field: &Option<Vec<String>>) -> Option<T> {
+    field.as_ref()?.first()?.parse().ok()
+}
+
+// Read the TCP and UDP payloads from the JSON output of tshark: the addresses
+// and sequence numbers are not exported, the directions are told apart by port
+async fn read_json(file: String) -> Vec<Flow> {
+    let contents = fs::read_to_string(file).await.unwrap();
+    let pkts: Vec<PcapData> = serde_json::from_str(&contents).unwrap();
+    let mut flows = Flows::default();
+
+    for pkt in pkts.iter() {
+        let layers = &pkt.source.layers;
+        let timestamp = Duration::from_secs_f64(first(&layers.frame_time).unwrap_or(0.0));
+        let frame_len = first(&layers.frame_len).unwrap_or(0);
+        let ip_len = first(&layers.ip_len).unwrap_or(0);
+        if let Some(payload) = &layers.tcp_payload {
+            let p = payload.first().unwrap();
+            let d = hex::decode(p).unwrap();
+            let src = SocketAddr::from(([0, 0, 0, 0], first(&layers.tcp_src).unwrap_or(0)));
+            let dst = SocketAddr::from(([0, 0, 0, 0], first(&layers.tcp_dest).unwrap_or(0)));
+            flows.push_unsequenced(src, dst, timestamp, frame_len, ip_len, &d);
+        } else if let Some(payload) = &layers.udp_payload {
+            let p = payload.first().unwrap();
+            let d = hex::decode(p).unwrap();
+            let src = SocketAddr::from(([0, 0, 0, 0], first(&layers.udp_src).unwrap_or(0)));
+            let dst = SocketAddr::from(([0, 0, 0, 0], first(&layers.udp_dest).unwrap_or(0)));
+            flows.push_datagram(src, dst, timestamp, frame_len, ip_len, &d);
+        }
+    }
+    flows.finish()
+}
+
+// Reassemble the TCP streams and collect the UDP datagrams to and from the
+// given port in a pcap or pcapng capture file
+fn read_pcap(file: String, port: u16) -> Vec<Flow> {
+    let reader = PcapReader::new(BufReader::new(File::open(file).unwrap())).unwrap();
+    let mut flows = Flows::default();
+
+    for pkt in reader {
+        let pkt = pkt.unwrap();
+        if pkt.src.port() == port || pkt.dst.port() == port {
+            flows.push(&pkt);
+        }
+    }
+    flows.finish()
+}
+
+fn analyze(flow: Flow, batch_size: usize, interval: f64) -> Stats {
+    let mut stats = Stats::new(batch_size, interval);
+    stats.wire_size = flow.wire_size;
+    stats.ip_size = flow.ip_size;
+    stats.zenoh_size = flow.size();
+    stats.gaps = flow.gaps;
+    stats.missing = flow.missing;
+    stats.retransmitted = flow.retransmitted;
+    for (timestamp, frame_len) in flow.packets.iter() {
+        stats.add_packet(*timestamp, *frame_len);
+    }
+
+    // The framing is followed independently on each contiguous chunk, those
+    // not starting on a batch boundary cannot be decoded
+    for chunk in flow.chunks.iter() {
+        if flow.framed && !chunk.synchronized {
+            stats.skipped += chunk.data.len();
+            continue;
+        }
+        let session_messages = if flow.framed {
+            read_session_messages(&chunk.data)
+        } else {
+            read_datagram_messages(&chunk.data)
+        };
+        for (offset, m) in session_messages.iter() {
+            stats.add_session_message(chunk.time(*offset), m);
+        }
+    }
+    stats
+}
+
+#[async_std::main]
+async fn main() {
+    // initiate logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    if opt.batch_size == 0 || opt.interval <= 0.0 {
+        panic!("The batch size and the interval must be positive");
+    }
+
+    let flows = match (opt.json, opt.pcap) {
+        (Some(file), None) => read_json(file).await,
+        (None, Some(file)) => read_pcap(file, opt.port),
+        _ => panic!("Either a JSON or a pcap file must be provided"),
+    };
+
+    let mut total = Stats::default();
+    for flow in flows {
+        // Skip the directions carrying only acknowledgments
+        if flow.size() == 0 {
+            continue;
+        }
+        let (src, dst) = (flow.src, flow.dst);
+        let transport = if flow.framed { "tcp" } else { "udp" };
+        let stats = analyze(flow, opt.batch_size, opt.interval);
+        if !opt.csv {
+            println!("Flow {}/{} -> {}", transport, src, dst);
+            stats.print();
+            println!();
+        }
+        total.add(&stats);
+    }
+
+    if opt.csv {
+        total.print_csv();
+    } else {
+        println!("All flows");
+        total.print();
+    }
+}
@@ -0,0 +1,685 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use std::convert::TryInto;
+use std::io::{self, ErrorKind, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+// Magic numbers of the capture file formats
+const PCAP_MAGIC_US: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NS: u32 = 0xa1b2_3c4d;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+
+// pcapng block types
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const PCAPNG_SIMPLE_PACKET: u32 = 0x0000_0003;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+
+// The bounds of the captured lengths and of the pcapng blocks, as in libpcap:
+// the lengths read are checked against them before allocating
+const MAX_SNAPLEN: usize = 262_144;
+const MAX_BLOCK_LEN: usize = 16 * 1024 * 1024;
+
+// pcapng interface description options
+const PCAPNG_OPT_ENDOFOPT: u16 = 0;
+const PCAPNG_OPT_IF_TSRESOL: u16 = 9;
+
+// Link-layer header types, see https://www.tcpdump.org/linktypes.html
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW_BSD: u32 = 12;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LOOP: u32 = 108;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+
+const IPPROTO_HOPOPTS: u8 = 0;
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+const IPPROTO_ROUTING: u8 = 43;
+const IPPROTO_FRAGMENT: u8 = 44;
+const IPPROTO_DSTOPTS: u8 = 60;
+
+pub const TCP_SYN: u8 = 0x02;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transport {
+    Tcp { seq: u32, flags: u8 },
+    Udp,
+}
+
+// A TCP segment or UDP datagram extracted from a captured frame
+#[derive(Debug, Clone)]
+pub struct Packet {
+    // The capture time, since the UNIX epoch
+    pub timestamp: Duration,
+    // The length of the frame on the wire
+    pub frame_len: usize,
+    // The length of the IP packet, headers included
+    pub ip_len: usize,
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub transport: Transport,
+    pub payload: Vec<u8>,
+}
+
+// A pcapng capture interface
+struct Interface {
+    linktype: u32,
+    // The timestamp units per second
+    resolution: u64,
+}
+
+enum Format {
+    Pcap {
+        big_endian: bool,
+        nanos: bool,
+        linktype: u32,
+        snaplen: usize,
+    },
+    PcapNg {
+        big_endian: bool,
+        interfaces: Vec<Interface>,
+    },
+}
+
+// A captured frame, with its length on the wire and its captured bytes
+struct Frame {
+    linktype: u32,
+    timestamp: Duration,
+    len: usize,
+    data: Vec<u8>,
+}
+
+// Streaming reader of pcap and pcapng files
+pub struct PcapReader<R: Read> {
+    reader: R,
+    format: Format,
+    // The timestamp of the last frame, for the frames that do not carry one
+    timestamp: Duration,
+}
+
+// Read an integer, None if the data is too short
+fn u16_at(data: &[u8], offset: usize, big_endian: bool) -> Option<u16> {
+    let bytes: [u8; 2] = data.get(offset..offset.checked_add(2)?)?.try_into().ok()?;
+    Some(if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+// Read an integer, None if the data is too short
+fn u32_at(data: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = data.get(offset..offset.checked_add(4)?)?.try_into().ok()?;
+    Some(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+// Convert a timestamp in the given units per second
+fn to_duration(ts: u64, resolution: u64) -> Duration {
+    let nanos = ts as u128 * 1_000_000_000 / resolution as u128;
+    Duration::from_nanos(nanos as u64)
+}
+
+// Parse the timestamp resolution option of an interface description block
+fn parse_resolution(body: &[u8], big_endian: bool) -> u64 {
+    // The options follow the link type, reserved and snaplen fields
+    let mut offset = 8;
+    while let (Some(code), Some(length)) = (
+        u16_at(body, offset, big_endian),
+        u16_at(body, offset + 2, big_endian),
+    ) {
+        let length = length as usize;
+        if code == PCAPNG_OPT_ENDOFOPT {
+            break;
+        }
+        if code == PCAPNG_OPT_IF_TSRESOL && length == 1 {
+            if let Some(&tsresol) = body.get(offset + 4) {
+                // The most significant bit tells a power of 2 from a power of 10
+                let exp = (tsresol & 0x7f) as u32;
+                return if tsresol & 0x80 != 0 {
+                    2u64.checked_pow(exp).unwrap_or(u64::MAX)
+                } else {
+                    10u64.checked_pow(exp).unwrap_or(u64::MAX)
+                };
+            }
+        }
+        // The option values are padded to 32 bits
+        offset += 4 + ((length + 3) & !3);
+    }
+    // Microseconds by default
+    1_000_000
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+fn truncated() -> io::Error {
+    invalid("Truncated capture block")
+}
+
+impl<R: Read> PcapReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        match u32::from_le_bytes(magic) {
+            PCAPNG_SECTION_HEADER => {
+                let mut pcap = Self {
+                    reader,
+                    format: Format::PcapNg {
+                        big_endian: false,
+                        interfaces: vec![],
+                    },
+                    timestamp: Duration::default(),
+                };
+                pcap.read_section_header()?;
+                Ok(pcap)
+            }
+            m => {
+                let big_endian = if m == PCAP_MAGIC_US || m == PCAP_MAGIC_NS {
+                    false
+                } else if m.swap_bytes() == PCAP_MAGIC_US || m.swap_bytes() == PCAP_MAGIC_NS {
+                    true
+                } else {
+                    return Err(invalid("Unknown capture file format"));
+                };
+                let nanos = m == PCAP_MAGIC_NS || m.swap_bytes() == PCAP_MAGIC_NS;
+                // Skip the version, thiszone and sigfigs fields
+                let mut header = [0u8; 20];
+                reader.read_exact(&mut header)?;
+                // Some writers leave the snaplen to 0, it is bounded either way
+                let snaplen = u32_at(&header, 12, big_endian).ok_or_else(truncated)? as usize;
+                let snaplen = snaplen.max(u16::MAX as usize).min(MAX_SNAPLEN);
+                // The upper bits of the link type may carry the FCS length
+                let linktype = u32_at(&header, 16, big_endian).ok_or_else(truncated)? & 0xffff;
+                Ok(Self {
+                    reader,
+                    format: Format::Pcap {
+                        big_endian,
+                        nanos,
+                        linktype,
+                        snaplen,
+                    },
+                    timestamp: Duration::default(),
+                })
+            }
+        }
+    }
+
+    // Read the rest of a pcapng section header block, its type being already
+    // consumed: the byte-order magic defines the endianness of the section
+    fn read_section_header(&mut self) -> io::Result<()> {
+        let mut header = [0u8; 8];
+        self.reader.read_exact(&mut header)?;
+        let big_endian = match u32::from_le_bytes(header[4..8].try_into().unwrap()) {
+            PCAPNG_BYTE_ORDER_MAGIC => false,
+            m if m.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+            _ => return Err(invalid("Invalid pcapng byte-order magic")),
+        };
+        let length = u32_at(&header, 0, big_endian).ok_or_else(truncated)? as usize;
+        if length < 16 || length > MAX_BLOCK_LEN {
+            return Err(invalid("Invalid pcapng section header length"));
+        }
+        let mut rest = vec![0u8; length - 12];
+        self.reader.read_exact(&mut rest)?;
+        // Interfaces are numbered per section
+        self.format = Format::PcapNg {
+            big_endian,
+            interfaces: vec![],
+        };
+        Ok(())
+    }
+
+    // Read the next captured frame
+    fn next_frame(&mut self) -> io::Result<Option<Frame>> {
+        loop {
+            match &mut self.format {
+                Format::Pcap {
+                    big_endian,
+                    nanos,
+                    linktype,
+                    snaplen,
+                } => {
+                    let mut header = [0u8; 16];
+                    match self.reader.read_exact(&mut header) {
+                        Ok(_) => {}
+                        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                        Err(e) => return Err(e),
+                    }
+                    let secs = u32_at(&header, 0, *big_endian).ok_or_else(truncated)? as u64;
+                    let frac = u32_at(&header, 4, *big_endian).ok_or_else(truncated)? as u64;
+                    let caplen = u32_at(&header, 8, *big_endian).ok_or_else(truncated)? as usize;
+                    let origlen = u32_at(&header, 12, *big_endian).ok_or_else(truncated)? as usize;
+                    if caplen > *snaplen {
+                        return Err(invalid("Invalid pcap captured length"));
+                    }
+                    let mut data = vec![0u8; caplen];
+                    self.reader.read_exact(&mut data)?;
+                    let frac = if *nanos {
+                        Duration::from_nanos(frac)
+                    } else {
+                        Duration::from_micros(frac)
+                    };
+                    return Ok(Some(Frame {
+                        linktype: *linktype,
+                        timestamp: Duration::from_secs(secs) + frac,
+                        len: origlen,
+                        data,
+                    }));
+                }
+                Format::PcapNg {
+                    big_endian,
+                    interfaces,
+                } => {
+                    let big_endian = *big_endian;
+                    let mut kind = [0u8; 4];
+                    match self.reader.read_exact(&mut kind) {
+                        Ok(_) => {}
+                        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                        Err(e) => return Err(e),
+                    }
+                    let kind = u32_at(&kind, 0, big_endian).ok_or_else(truncated)?;
+                    if kind == PCAPNG_SECTION_HEADER {
+                        self.read_section_header()?;
+                        continue;
+                    }
+
+                    let mut length = [0u8; 4];
+                    self.reader.read_exact(&mut length)?;
+                    let length = u32_at(&length, 0, big_endian).ok_or_else(truncated)? as usize;
+                    if length < 12 || length > MAX_BLOCK_LEN {
+                        return Err(invalid("Invalid pcapng block length"));
+                    }
+                    let mut body = vec![0u8; length - 8];
+                    self.reader.read_exact(&mut body)?;
+                    // Drop the trailing copy of the block length
+                    body.truncate(length - 12);
+
+                    match kind {
+                        PCAPNG_INTERFACE_DESCRIPTION => {
+                            interfaces.push(Interface {
+                                linktype: u16_at(&body, 0, big_endian).ok_or_else(truncated)?
+                                    as u32,
+                                resolution: parse_resolution(&body, big_endian),
+                            });
+                        }
+                        PCAPNG_ENHANCED_PACKET => {
+                            let interface =
+                                u32_at(&body, 0, big_endian).ok_or_else(truncated)? as usize;
+                            let high = u32_at(&body, 4, big_endian).ok_or_else(truncated)? as u64;
+                            let low = u32_at(&body, 8, big_endian).ok_or_else(truncated)? as u64;
+                            let caplen =
+                                u32_at(&body, 12, big_endian).ok_or_else(truncated)? as usize;
+                            let origlen =
+                                u32_at(&body, 16, big_endian).ok_or_else(truncated)? as usize;
+                            let interface = interfaces
+                                .get(interface)
+                                .ok_or_else(|| invalid("Unknown pcapng interface"))?;
+                            let data = body
+                                .get(20..20usize.saturating_add(caplen))
+                                .ok_or_else(|| invalid("Invalid pcapng packet length"))?
+                                .to_vec();
+                            let timestamp = to_duration(high << 32 | low, interface.resolution);
+                            self.timestamp = timestamp;
+                            return Ok(Some(Frame {
+                                linktype: interface.linktype,
+                                timestamp,
+                                len: origlen,
+                                data,
+                            }));
+                        }
+                        PCAPNG_SIMPLE_PACKET => {
+                            let origlen =
+                                u32_at(&body, 0, big_endian).ok_or_else(truncated)? as usize;
+                            // The captured length is bounded by the block length
+                            let caplen = origlen.min(body.len() - 4);
+                            let interface = interfaces
+                                .first()
+                                .ok_or_else(|| invalid("Unknown pcapng interface"))?;
+                            let data = body.get(4..4 + caplen).ok_or_else(truncated)?.to_vec();
+                            // Simple packets are not timestamped
+                            return Ok(Some(Frame {
+                                linktype: interface.linktype,
+                                timestamp: self.timestamp,
+                                len: origlen,
+                                data,
+                            }));
+                        }
+                        // Statistics, name resolution and custom blocks
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    // Read the next TCP segment or UDP datagram, skipping the frames that do
+    // not carry any
+    pub fn next_packet(&mut self) -> io::Result<Option<Packet>> {
+        while let Some(frame) = self.next_frame()? {
+            if let Some(packet) = decode_frame(&frame) {
+                return Ok(Some(packet));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<R: Read> Iterator for PcapReader<R> {
+    type Item = io::Result<Packet>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_packet().transpose()
+    }
+}
+
+// Strip the link-layer header and decode the IP packet
+fn decode_frame(frame: &Frame) -> Option<Packet> {
+    let data = &frame.data[..];
+    let ip = match frame.linktype {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            let mut ethertype = u16_at(data, offset, true)?;
+            while ethertype == ETHERTYPE_VLAN || ethertype == ETHERTYPE_QINQ {
+                offset += 4;
+                ethertype = u16_at(data, offset, true)?;
+            }
+            match ethertype {
+                ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => data.get(offset + 2..)?,
+                _ => return None,
+            }
+        }
+        // The BSD loopback header is the address family in the byte order of
+        // the capturing host, which cannot be known: the IP version is used
+        LINKTYPE_NULL | LINKTYPE_LOOP => data.get(4..)?,
+        LINKTYPE_LINUX_SLL => data.get(16..)?,
+        LINKTYPE_LINUX_SLL2 => data.get(20..)?,
+        LINKTYPE_RAW | LINKTYPE_RAW_BSD | LINKTYPE_IPV4 | LINKTYPE_IPV6 => data,
+        _ => return None,
+    };
+    decode_ip(frame.timestamp, frame.len, ip)
+}
+
+fn decode_ip(timestamp: Duration, frame_len: usize, data: &[u8]) -> Option<Packet> {
+    let (src, dst, ip_len, protocol, transport) = match data.first()? >> 4 {
+        4 => {
+            let header = data.get(..20)?;
+            let ihl = (header[0] & 0x0f) as usize * 4;
+            // The total length is zeroed on frames captured before TCP
+            // segmentation offload
+            let ip_len = match u16_at(header, 2, true)? as usize {
+                0 => data.len(),
+                l => l,
+            };
+            // Only the first fragment carries the transport header
+            let fragment_offset = u16_at(header, 6, true)? & 0x1fff;
+            if fragment_offset != 0 {
+                return None;
+            }
+            let src = IpAddr::V4(Ipv4Addr::new(
+                header[12], header[13], header[14], header[15],
+            ));
+            let dst = IpAddr::V4(Ipv4Addr::new(
+                header[16], header[17], header[18], header[19],
+            ));
+            // Drop the link-layer padding, if any
+            let transport = data.get(ihl..ip_len.min(data.len()))?;
+            (src, dst, ip_len, header[9], transport)
+        }
+        6 => {
+            let header = data.get(..40)?;
+            let ip_len = 40 + u16_at(header, 4, true)? as usize;
+            let src: [u8; 16] = header[8..24].try_into().unwrap();
+            let dst: [u8; 16] = header[24..40].try_into().unwrap();
+            let mut next = header[6];
+            let mut offset = 40;
+            // Skip the extension headers
+            loop {
+                match next {
+                    IPPROTO_HOPOPTS | IPPROTO_ROUTING | IPPROTO_DSTOPTS => {
+                        let ext = data.get(offset..offset + 2)?;
+                        next = ext[0];
+                        offset += (ext[1] as usize + 1) * 8;
+                    }
+                    IPPROTO_FRAGMENT => return None,
+                    _ => break,
+                }
+            }
+            let transport = data.get(offset..ip_len.min(data.len()))?;
+            (
+                IpAddr::V6(Ipv6Addr::from(src)),
+                IpAddr::V6(Ipv6Addr::from(dst)),
+                ip_len,
+                next,
+                transport,
+            )
+        }
+        _ => return None,
+    };
+
+    match protocol {
+        IPPROTO_TCP => {
+            let header = transport.get(..20)?;
+            let offset = (header[12] >> 4) as usize * 4;
+            Some(Packet {
+                timestamp,
+                frame_len,
+                ip_len,
+                src: SocketAddr::new(src, u16_at(header, 0, true)?),
+                dst: SocketAddr::new(dst, u16_at(header, 2, true)?),
+                transport: Transport::Tcp {
+                    seq: u32_at(header, 4, true)?,
+                    flags: header[13],
+                },
+                payload: transport.get(offset..)?.to_vec(),
+            })
+        }
+        IPPROTO_UDP => {
+            let header = transport.get(..8)?;
+            let length = (u16_at(header, 4, true)? as usize).min(transport.len());
+            Some(Packet {
+                timestamp,
+                frame_len,
+                ip_len,
+                src: SocketAddr::new(src, u16_at(header, 0, true)?),
+                dst: SocketAddr::new(dst, u16_at(header, 2, true)?),
+                transport: Transport::Udp,
+                payload: transport.get(8..length)?.to_vec(),
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A raw IPv4 TCP segment from 10.0.0.1:1234 to 10.0.0.2:7447
+    fn tcp_segment(seq: u32, payload: &[u8]) -> Vec<u8> {
+        let mut ip = vec![0x45, 0, 0, 0, 0, 0, 0, 0, 64, IPPROTO_TCP, 0, 0];
+        ip[2..4].copy_from_slice(&((40 + payload.len()) as u16).to_be_bytes());
+        ip.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        ip.extend_from_slice(&1234u16.to_be_bytes());
+        ip.extend_from_slice(&7447u16.to_be_bytes());
+        ip.extend_from_slice(&seq.to_be_bytes());
+        ip.extend_from_slice(&[0, 0, 0, 0, 0x50, 0x18, 0, 0, 0, 0, 0, 0]);
+        ip.extend_from_slice(payload);
+        ip
+    }
+
+    fn pcap(frames: &[&[u8]]) -> Vec<u8> {
+        let mut file = PCAP_MAGIC_US.to_le_bytes().to_vec();
+        file.extend_from_slice(&[2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0]);
+        file.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+        for (i, frame) in frames.iter().enumerate() {
+            for field in &[i as u32, 500, frame.len() as u32, frame.len() as u32] {
+                file.extend_from_slice(&field.to_le_bytes());
+            }
+            file.extend_from_slice(frame);
+        }
+        file
+    }
+
+    fn block(kind: u32, body: &[u8]) -> Vec<u8> {
+        let length = (12 + body.len()) as u32;
+        let mut block = kind.to_le_bytes().to_vec();
+        block.extend_from_slice(&length.to_le_bytes());
+        block.extend_from_slice(body);
+        block.extend_from_slice(&length.to_le_bytes());
+        block
+    }
+
+    // A pcapng section with a raw IP interface, followed by the given blocks
+    fn pcapng(blocks: &[Vec<u8>]) -> Vec<u8> {
+        let mut shb = PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        shb.extend_from_slice(&[1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        let mut file = block(PCAPNG_SECTION_HEADER, &shb);
+        // Nanosecond timestamps
+        let idb = [101, 0, 0, 0, 0xff, 0xff, 0, 0, 9, 0, 1, 0, 9, 0, 0, 0];
+        file.extend(block(PCAPNG_INTERFACE_DESCRIPTION, &idb));
+        for b in blocks {
+            file.extend_from_slice(b);
+        }
+        file
+    }
+
+    fn enhanced_packet(ts: u64, frame: &[u8]) -> Vec<u8> {
+        let mut body = vec![0, 0, 0, 0];
+        body.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(ts as u32).to_le_bytes());
+        body.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        body.extend_from_slice(frame);
+        body.resize((body.len() + 3) & !3, 0);
+        block(PCAPNG_ENHANCED_PACKET, &body)
+    }
+
+    fn read(file: Vec<u8>) -> Vec<io::Result<Packet>> {
+        PcapReader::new(&file[..]).unwrap().collect()
+    }
+
+    #[test]
+    fn pcap_packets() {
+        let frame = tcp_segment(42, b"hi");
+        let packets = read(pcap(&[&frame, &[0x45, 0]]));
+        // The frames without a TCP or UDP header are skipped
+        assert_eq!(packets.len(), 1);
+        let packet = packets[0].as_ref().unwrap();
+        assert_eq!(packet.timestamp, Duration::from_micros(500));
+        assert_eq!(packet.src, "10.0.0.1:1234".parse().unwrap());
+        assert_eq!(packet.dst, "10.0.0.2:7447".parse().unwrap());
+        assert_eq!(
+            packet.transport,
+            Transport::Tcp {
+                seq: 42,
+                flags: 0x18
+            }
+        );
+        assert_eq!(packet.payload, b"hi");
+        assert_eq!((packet.frame_len, packet.ip_len), (42, 42));
+    }
+
+    #[test]
+    fn pcap_truncated_record() {
+        let mut file = pcap(&[&tcp_segment(0, b"hi")]);
+        file.truncate(file.len() - 1);
+        let packets = read(file);
+        assert_eq!(packets.len(), 1);
+        assert!(packets[0].is_err());
+    }
+
+    #[test]
+    fn pcap_oversized_record() {
+        let mut file = pcap(&[&tcp_segment(0, b"hi")]);
+        // The captured length of the record
+        file[32..36].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut reader = PcapReader::new(&file[..]).unwrap();
+        assert!(reader.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn pcapng_packets() {
+        let frame = tcp_segment(7, b"zenoh");
+        let packets = read(pcapng(&[enhanced_packet(1_500_000_000, &frame)]));
+        assert_eq!(packets.len(), 1);
+        let packet = packets[0].as_ref().unwrap();
+        assert_eq!(packet.timestamp, Duration::from_millis(1500));
+        assert_eq!(packet.payload, b"zenoh");
+    }
+
+    #[test]
+    fn pcapng_truncated_blocks() {
+        // Blocks too short for their fixed fields
+        for kind in &[
+            PCAPNG_INTERFACE_DESCRIPTION,
+            PCAPNG_ENHANCED_PACKET,
+            PCAPNG_SIMPLE_PACKET,
+        ] {
+            let packets = read(pcapng(&[block(*kind, &[])]));
+            assert_eq!(packets.len(), 1);
+            assert!(packets[0].is_err());
+        }
+        // A captured length beyond the block
+        let mut packet = enhanced_packet(0, &tcp_segment(0, b"hi"));
+        packet[20] = 0xff;
+        let packets = read(pcapng(&[packet]));
+        assert!(packets[0].is_err());
+        // A block length beyond the bound
+        let mut packet = enhanced_packet(0, &tcp_segment(0, b"hi"));
+        packet[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        let file = pcapng(&[packet]);
+        let mut reader = PcapReader::new(&file[..]).unwrap();
+        assert!(reader.next().unwrap().is_err());
+        // A block cut by the end of the file
+        let mut file = pcapng(&[enhanced_packet(0, &tcp_segment(0, b"hi"))]);
+        file.truncate(file.len() - 8);
+        let packets = read(file);
+        assert!(packets[0].is_err());
+    }
+
+    #[test]
+    fn truncated_frames() {
+        let frame = tcp_segment(0, b"hi");
+        for len in &[0, 1, 19, 20, 39] {
+            let frame = Frame {
+                linktype: LINKTYPE_RAW,
+                timestamp: Duration::default(),
+                len: *len,
+                data: frame[..*len].to_vec(),
+            };
+            assert!(decode_frame(&frame).is_none());
+        }
+        let frame = Frame {
+            linktype: LINKTYPE_ETHERNET,
+            timestamp: Duration::default(),
+            len: 13,
+            data: vec![0; 13],
+        };
+        assert!(decode_frame(&frame).is_none());
+    }
+}
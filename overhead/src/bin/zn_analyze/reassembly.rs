@@ -0,0 +1,402 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use crate::pcap::{Packet, Transport, TCP_SYN};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+// The out-of-order bytes buffered per flow before giving up on the holes
+const MAX_PENDING: usize = 16 * 1024 * 1024;
+
+// A contiguous run of bytes
+pub struct Chunk {
+    pub data: Vec<u8>,
+    // Whether the run starts on a batch boundary: it does not after a gap or
+    // when the capture starts in the middle of a connection
+    pub synchronized: bool,
+    // The offsets at which the bytes of each segment start, with their
+    // capture time
+    marks: Vec<(usize, Duration)>,
+}
+
+impl Chunk {
+    fn new(synchronized: bool) -> Self {
+        Self {
+            data: vec![],
+            synchronized,
+            marks: vec![],
+        }
+    }
+
+    fn extend(&mut self, timestamp: Duration, bytes: &[u8]) {
+        self.marks.push((self.data.len(), timestamp));
+        self.data.extend_from_slice(bytes);
+    }
+
+    // The capture time of the byte at the given offset
+    pub fn time(&self, offset: usize) -> Duration {
+        let i = match self.marks.binary_search_by_key(&offset, |m| m.0) {
+            Ok(i) => i,
+            Err(i) => i.saturating_sub(1),
+        };
+        self.marks.get(i).map(|m| m.1).unwrap_or_default()
+    }
+}
+
+// One direction of a TCP connection, reassembled in sequence order, or of a
+// UDP flow
+pub struct Flow {
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    // Whether the session messages are prefixed by their length, as on TCP
+    pub framed: bool,
+    // The contiguous runs of bytes, a new one is started after every gap, or
+    // the datagrams when not framed
+    pub chunks: Vec<Chunk>,
+    // The capture time and the size on the wire of every packet
+    pub packets: Vec<(Duration, usize)>,
+    pub gaps: usize,
+    pub missing: usize,
+    pub retransmitted: usize,
+    pub wire_size: usize,
+    pub ip_size: usize,
+    // The sequence number of the first byte of the stream
+    isn: Option<u32>,
+    // The next expected byte, relative to the initial sequence number
+    next: u32,
+    // The out-of-order segments, keyed by their relative sequence number
+    pending: BTreeMap<u32, (Duration, Vec<u8>)>,
+    // The size of the out-of-order segments
+    pending_size: usize,
+}
+
+impl Flow {
+    fn new(src: SocketAddr, dst: SocketAddr, framed: bool) -> Self {
+        Self {
+            src,
+            dst,
+            framed,
+            chunks: if framed {
+                vec![Chunk::new(true)]
+            } else {
+                vec![]
+            },
+            packets: vec![],
+            gaps: 0,
+            missing: 0,
+            retransmitted: 0,
+            wire_size: 0,
+            ip_size: 0,
+            isn: None,
+            next: 0,
+            pending: BTreeMap::new(),
+            pending_size: 0,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.chunks.iter().map(|c| c.data.len()).sum()
+    }
+
+    // Append the bytes of a segment starting at the relative sequence number
+    // `rel`, skipping those already received
+    fn append(&mut self, rel: u32, timestamp: Duration, payload: &[u8]) {
+        let end = rel.wrapping_add(payload.len() as u32);
+        let skip = self.next.wrapping_sub(rel) as usize;
+        if skip >= payload.len() {
+            self.retransmitted += payload.len();
+            return;
+        }
+        self.retransmitted += skip;
+        self.chunks
+            .last_mut()
+            .unwrap()
+            .extend(timestamp, &payload[skip..]);
+        self.next = end;
+    }
+
+    // Append the pending segments that are now in sequence
+    fn drain(&mut self) {
+        while let Some((&rel, _)) = self.pending.iter().next() {
+            if (rel.wrapping_sub(self.next) as i32) > 0 {
+                break;
+            }
+            let (timestamp, payload) = self.pending.remove(&rel).unwrap();
+            self.pending_size -= payload.len();
+            self.append(rel, timestamp, &payload);
+        }
+    }
+
+    fn push(&mut self, seq: u32, flags: u8, timestamp: Duration, payload: &[u8]) {
+        if flags & TCP_SYN != 0 {
+            // The SYN consumes one sequence number
+            let isn = seq.wrapping_add(1);
+            match self.isn {
+                None => self.isn = Some(isn),
+                // A retransmitted SYN
+                Some(i) if i == isn => {}
+                // The 4-tuple is reused by a new connection
+                Some(_) => self.reset(isn),
+            }
+        }
+        let isn = match self.isn {
+            Some(isn) => isn,
+            None => {
+                // The capture starts in the middle of the connection
+                self.chunks.last_mut().unwrap().synchronized = false;
+                *self.isn.get_or_insert(seq)
+            }
+        };
+        if payload.is_empty() {
+            return;
+        }
+
+        let rel = seq.wrapping_sub(isn);
+        if (rel.wrapping_sub(self.next) as i32) > 0 {
+            // A previous segment is missing or has been reordered
+            self.pending_size += payload.len();
+            if let Some((_, old)) = self.pending.insert(rel, (timestamp, payload.to_vec())) {
+                self.pending_size -= old.len();
+                self.retransmitted += old.len();
+            }
+            // Give up on the holes rather than buffering the rest of the stream
+            while self.pending_size > MAX_PENDING {
+                self.skip_hole();
+            }
+        } else {
+            self.append(rel, timestamp, payload);
+            self.drain();
+        }
+    }
+
+    // Give up on the first hole: the bytes after it start a new chunk since
+    // the framing cannot be followed across it
+    fn skip_hole(&mut self) {
+        if let Some((&rel, _)) = self.pending.iter().next() {
+            let hole = rel.wrapping_sub(self.next);
+            if (hole as i32) > 0 {
+                self.gaps += 1;
+                self.missing += hole as usize;
+                self.chunks.push(Chunk::new(false));
+                self.next = rel;
+            }
+            self.drain();
+        }
+    }
+
+    // Give up on the segments that never arrived
+    fn flush(&mut self) {
+        while !self.pending.is_empty() {
+            self.skip_hole();
+        }
+    }
+
+    // Start over on a new connection
+    fn reset(&mut self, isn: u32) {
+        self.flush();
+        self.chunks.push(Chunk::new(true));
+        self.isn = Some(isn);
+        self.next = 0;
+    }
+}
+
+// The flows of a capture, per connection and direction
+#[derive(Default)]
+pub struct Flows {
+    flows: HashMap<(SocketAddr, SocketAddr, bool), Flow>,
+    // The directions in the order they first appear in the capture
+    order: Vec<(SocketAddr, SocketAddr, bool)>,
+}
+
+impl Flows {
+    fn flow(&mut self, src: SocketAddr, dst: SocketAddr, framed: bool) -> &mut Flow {
+        let order = &mut self.order;
+        self.flows.entry((src, dst, framed)).or_insert_with(|| {
+            order.push((src, dst, framed));
+            Flow::new(src, dst, framed)
+        })
+    }
+
+    pub fn push(&mut self, pkt: &Packet) {
+        match pkt.transport {
+            Transport::Tcp { seq, flags } => {
+                let flow = self.flow(pkt.src, pkt.dst, true);
+                flow.wire_size += pkt.frame_len;
+                flow.ip_size += pkt.ip_len;
+                flow.packets.push((pkt.timestamp, pkt.frame_len));
+                flow.push(seq, flags, pkt.timestamp, &pkt.payload);
+            }
+            Transport::Udp => self.push_datagram(
+                pkt.src,
+                pkt.dst,
+                pkt.timestamp,
+                pkt.frame_len,
+                pkt.ip_len,
+                &pkt.payload,
+            ),
+        }
+    }
+
+    pub fn push_datagram(
+        &mut self,
+        src: SocketAddr,
+        dst: SocketAddr,
+        timestamp: Duration,
+        frame_len: usize,
+        ip_len: usize,
+        payload: &[u8],
+    ) {
+        let flow = self.flow(src, dst, false);
+        flow.wire_size += frame_len;
+        flow.ip_size += ip_len;
+        flow.packets.push((timestamp, frame_len));
+        let mut chunk = Chunk::new(true);
+        chunk.extend(timestamp, payload);
+        flow.chunks.push(chunk);
+    }
+
+    // Append a payload whose sequence number is unknown, trusting the order
+    // of the capture
+    pub fn push_unsequenced(
+        &mut self,
+        src: SocketAddr,
+        dst: SocketAddr,
+        timestamp: Duration,
+        frame_len: usize,
+        ip_len: usize,
+        payload: &[u8],
+    ) {
+        let flow = self.flow(src, dst, true);
+        flow.wire_size += frame_len;
+        flow.ip_size += ip_len;
+        flow.packets.push((timestamp, frame_len));
+        flow.chunks.last_mut().unwrap().extend(timestamp, payload);
+    }
+
+    pub fn finish(mut self) -> Vec<Flow> {
+        let mut flows = vec![];
+        for key in self.order.iter() {
+            let mut flow = self.flows.remove(key).unwrap();
+            flow.flush();
+            flows.push(flow);
+        }
+        flows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYN: u32 = 1000;
+
+    fn flow() -> Flow {
+        Flow::new(
+            "127.0.0.1:1".parse().unwrap(),
+            "127.0.0.1:2".parse().unwrap(),
+            true,
+        )
+    }
+
+    fn push(flow: &mut Flow, rel: u32, payload: &[u8]) {
+        let seq = SYN.wrapping_add(1).wrapping_add(rel);
+        flow.push(seq, 0, Duration::from_millis(rel as u64), payload);
+    }
+
+    fn chunks(flow: &Flow) -> Vec<(&[u8], bool)> {
+        flow.chunks
+            .iter()
+            .map(|c| (&c.data[..], c.synchronized))
+            .collect()
+    }
+
+    #[test]
+    fn in_order() {
+        let mut flow = flow();
+        flow.push(SYN, TCP_SYN, Duration::default(), &[]);
+        push(&mut flow, 0, b"abc");
+        push(&mut flow, 3, b"def");
+        flow.flush();
+        assert_eq!(chunks(&flow), vec![(&b"abcdef"[..], true)]);
+        assert_eq!(flow.chunks[0].time(4), Duration::from_millis(3));
+        assert_eq!(flow.gaps, 0);
+    }
+
+    #[test]
+    fn out_of_order_and_retransmitted() {
+        let mut flow = flow();
+        flow.push(SYN, TCP_SYN, Duration::default(), &[]);
+        push(&mut flow, 3, b"def");
+        push(&mut flow, 3, b"def");
+        push(&mut flow, 0, b"abc");
+        push(&mut flow, 1, b"bcd");
+        flow.flush();
+        assert_eq!(chunks(&flow), vec![(&b"abcdef"[..], true)]);
+        assert_eq!(flow.gaps, 0);
+        assert_eq!(flow.retransmitted, 6);
+    }
+
+    #[test]
+    fn gap() {
+        let mut flow = flow();
+        flow.push(SYN, TCP_SYN, Duration::default(), &[]);
+        push(&mut flow, 0, b"abc");
+        push(&mut flow, 5, b"fg");
+        flow.flush();
+        assert_eq!(
+            chunks(&flow),
+            vec![(&b"abc"[..], true), (&b"fg"[..], false)]
+        );
+        assert_eq!((flow.gaps, flow.missing), (1, 2));
+    }
+
+    #[test]
+    fn capture_started_mid_connection() {
+        let mut flow = flow();
+        push(&mut flow, 0, b"abc");
+        flow.flush();
+        assert_eq!(chunks(&flow), vec![(&b"abc"[..], false)]);
+    }
+
+    #[test]
+    fn syn_resets_the_flow() {
+        let mut flow = flow();
+        flow.push(SYN, TCP_SYN, Duration::default(), &[]);
+        push(&mut flow, 0, b"abc");
+        // A retransmitted SYN does not
+        flow.push(SYN, TCP_SYN, Duration::default(), &[]);
+        flow.push(5000, TCP_SYN, Duration::default(), &[]);
+        flow.push(5001, 0, Duration::default(), b"xyz");
+        flow.flush();
+        assert_eq!(
+            chunks(&flow),
+            vec![(&b"abc"[..], true), (&b"xyz"[..], true)]
+        );
+        assert_eq!(flow.gaps, 0);
+    }
+
+    #[test]
+    fn bounded_pending() {
+        let mut flow = flow();
+        flow.push(SYN, TCP_SYN, Duration::default(), &[]);
+        let segment = vec![0u8; 1024 * 1024];
+        for i in 0..20 {
+            push(&mut flow, 1 + i * segment.len() as u32, &segment);
+        }
+        assert!(flow.pending_size <= MAX_PENDING);
+        assert_eq!((flow.gaps, flow.missing), (1, 1));
+        flow.flush();
+        assert_eq!(flow.size(), 20 * segment.len());
+    }
+}
@@ -0,0 +1,530 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::channel::{bounded, Receiver, Sender};
+use async_std::future;
+use async_std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use async_std::prelude::*;
+use async_std::sync::Arc;
+use async_std::task;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::io;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+
+// The packets read ahead of their scheduling on a direction
+const CHANNEL_CAPACITY: usize = 64;
+// The bytes held in flight on a direction, reading stops beyond them
+const MAX_IN_FLIGHT: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "zn_netem")]
+struct Opt {
+    #[structopt(short = "t", long = "transport", default_value = "tcp")]
+    transport: String,
+    #[structopt(short = "l", long = "listen", default_value = "127.0.0.1:7448")]
+    listen: SocketAddr,
+    #[structopt(short = "p", long = "peer", default_value = "127.0.0.1:7447")]
+    peer: SocketAddr,
+    // One-way delay in milliseconds, applied in both directions
+    #[structopt(short = "d", long = "delay", default_value = "0")]
+    delay: f64,
+    // Jitter in milliseconds, its meaning depends on the distribution
+    #[structopt(short = "j", long = "jitter", default_value = "0")]
+    jitter: f64,
+    // uniform: +/- jitter, normal: jitter is the standard deviation,
+    // pareto: jitter is the mean of a heavy tailed extra delay
+    #[structopt(long = "distribution", default_value = "uniform")]
+    distribution: String,
+    // Percentage of the datagrams that are dropped, UDP only
+    #[structopt(long = "loss", default_value = "0")]
+    loss: f64,
+    // Percentage of the datagrams that are sent without delay, overtaking the
+    // delayed ones, UDP only
+    #[structopt(long = "reorder", default_value = "0")]
+    reorder: f64,
+    // Bandwidth cap in Mbit/s, 0 for none
+    #[structopt(short = "b", long = "bandwidth", default_value = "0")]
+    bandwidth: f64,
+    #[structopt(long = "seed")]
+    seed: Option<u64>,
+}
+
+#[derive(Clone, Copy)]
+enum Distribution {
+    Uniform,
+    Normal,
+    Pareto,
+}
+
+// The impairments of the emulated link
+#[derive(Clone, Copy)]
+struct Netem {
+    delay: f64,
+    jitter: f64,
+    distribution: Distribution,
+    loss: f64,
+    reorder: f64,
+    // In bytes per second
+    bandwidth: Option<f64>,
+}
+
+// The state of one direction of the emulated link
+struct Link {
+    netem: Netem,
+    rng: StdRng,
+    // When the link is done serializing the previous packets
+    free_at: Instant,
+    // When the previous packet is delivered, the streams are kept in order
+    last: Instant,
+    ordered: bool,
+}
+
+impl Link {
+    fn new(netem: Netem, seed: u64, ordered: bool) -> Self {
+        let now = Instant::now();
+        Self {
+            netem,
+            rng: StdRng::seed_from_u64(seed),
+            free_at: now,
+            last: now,
+            ordered,
+        }
+    }
+
+    // Sample the jitter in milliseconds
+    fn jitter(&mut self) -> f64 {
+        let jitter = self.netem.jitter;
+        if jitter == 0.0 {
+            return 0.0;
+        }
+        match self.netem.distribution {
+            Distribution::Uniform => self.rng.gen_range(-jitter..=jitter),
+            Distribution::Normal => {
+                // Box-Muller transform
+                let u1: f64 = 1.0 - self.rng.gen::<f64>();
+                let u2: f64 = self.rng.gen();
+                jitter * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+            }
+            Distribution::Pareto => {
+                // Shape 3, scaled for the extra delay to average the jitter
+                let u: f64 = 1.0 - self.rng.gen::<f64>();
+                2.0 * jitter * (u.powf(-1.0 / 3.0) - 1.0)
+            }
+        }
+    }
+
+    // How long the link needs to serialize the packets already scheduled, the
+    // reader waits for it not to get ahead of the bandwidth
+    fn backlog(&self, now: Instant) -> Duration {
+        self.free_at.saturating_duration_since(now)
+    }
+
+    // The time at which a packet of the given size received at the given time
+    // is delivered, none if it is lost
+    fn schedule(&mut self, now: Instant, size: usize) -> Option<Instant> {
+        if self.netem.loss > 0.0 && self.rng.gen::<f64>() * 100.0 < self.netem.loss {
+            return None;
+        }
+
+        // The packet leaves once the link has serialized it
+        let mut sent = now;
+        if let Some(bandwidth) = self.netem.bandwidth {
+            sent = self.free_at.max(now) + Duration::from_secs_f64(size as f64 / bandwidth);
+            self.free_at = sent;
+        }
+
+        let at = if self.netem.reorder > 0.0 && self.rng.gen::<f64>() * 100.0 < self.netem.reorder {
+            sent
+        } else {
+            let delay = (self.netem.delay + self.jitter()).max(0.0);
+            sent + Duration::from_secs_f64(delay / 1000.0)
+        };
+        if self.ordered {
+            self.last = self.last.max(at);
+            Some(self.last)
+        } else {
+            Some(at)
+        }
+    }
+}
+
+// Where the delayed packets of a direction are delivered
+enum Output {
+    Tcp(TcpStream),
+    Udp(Arc<UdpSocket>, SocketAddr),
+}
+
+impl Output {
+    async fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match self {
+            Output::Tcp(stream) => stream.write_all(bytes).await,
+            Output::Udp(socket, to) => socket.send_to(bytes, *to).await.map(|_| ()),
+        }
+    }
+
+    fn close(&self) {
+        if let Output::Tcp(stream) = self {
+            let _ = stream.shutdown(Shutdown::Write);
+        }
+    }
+}
+
+// Deliver the packets received on one direction once their time has come,
+// no more are received while too many bytes are in flight for the channel to
+// push back on the reader
+async fn deliver(rx: Receiver<(Instant, u64, Vec<u8>)>, mut output: Output) {
+    let mut queue: BinaryHeap<Reverse<(Instant, u64, Vec<u8>)>> = BinaryHeap::new();
+    let mut in_flight = 0;
+    let mut open = true;
+    while open || !queue.is_empty() {
+        let next = queue.peek().map(|Reverse((at, _, _))| *at);
+        let receiving = open && in_flight < MAX_IN_FLIGHT;
+        let received = match next {
+            Some(at) if at <= Instant::now() => None,
+            Some(at) if receiving => {
+                match future::timeout(at.saturating_duration_since(Instant::now()), rx.recv()).await
+                {
+                    Ok(Ok(packet)) => Some(packet),
+                    Ok(Err(_)) => {
+                        open = false;
+                        None
+                    }
+                    Err(_) => None,
+                }
+            }
+            Some(at) => {
+                task::sleep(at.saturating_duration_since(Instant::now())).await;
+                None
+            }
+            None => match rx.recv().await {
+                Ok(packet) => Some(packet),
+                Err(_) => {
+                    open = false;
+                    None
+                }
+            },
+        };
+
+        match received {
+            Some(packet) => {
+                in_flight += packet.2.len();
+                queue.push(Reverse(packet));
+            }
+            None => {
+                let due =
+                    matches!(queue.peek(), Some(Reverse((at, _, _))) if *at <= Instant::now());
+                if due {
+                    let Reverse((_, _, bytes)) = queue.pop().unwrap();
+                    in_flight -= bytes.len();
+                    if output.send(&bytes).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    output.close();
+}
+
+// Read one direction of a TCP connection and schedule its bytes on the link
+async fn forward(mut from: TcpStream, mut link: Link, tx: Sender<(Instant, u64, Vec<u8>)>) {
+    let mut buffer = vec![0u8; 65_536];
+    let mut count: u64 = 0;
+    loop {
+        let n = match from.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        let now = Instant::now();
+        if let Some(at) = link.schedule(now, n) {
+            if tx.send((at, count, buffer[..n].to_vec())).await.is_err() {
+                break;
+            }
+        }
+        count += 1;
+        task::sleep(link.backlog(now)).await;
+    }
+}
+
+async fn netem_tcp(listen: SocketAddr, peer: SocketAddr, netem: Netem, seed: u64) {
+    let listener = TcpListener::bind(listen).await.unwrap();
+    let mut incoming = listener.incoming();
+    let mut connections: u64 = 0;
+    while let Some(stream) = incoming.next().await {
+        let client = stream.unwrap();
+        let upstream = TcpStream::connect(peer).await.unwrap();
+        let seed = seed.wrapping_add(2 * connections);
+        connections += 1;
+
+        let (to_peer, rx) = bounded(CHANNEL_CAPACITY);
+        task::spawn(deliver(rx, Output::Tcp(upstream.clone())));
+        task::spawn(forward(
+            client.clone(),
+            Link::new(netem, seed, true),
+            to_peer,
+        ));
+
+        let (to_client, rx) = bounded(CHANNEL_CAPACITY);
+        task::spawn(deliver(rx, Output::Tcp(client)));
+        task::spawn(forward(
+            upstream,
+            Link::new(netem, seed.wrapping_add(1), true),
+            to_client,
+        ));
+    }
+}
+
+// Schedule a datagram received on one direction of a UDP link
+async fn forward_datagram(
+    link: &mut Link,
+    count: &mut u64,
+    tx: &Sender<(Instant, u64, Vec<u8>)>,
+    bytes: &[u8],
+) {
+    let now = Instant::now();
+    if let Some(at) = link.schedule(now, bytes.len()) {
+        let _ = tx.send((at, *count, bytes.to_vec())).await;
+    }
+    *count += 1;
+    task::sleep(link.backlog(now)).await;
+}
+
+// Forward the datagrams of each client to the peer from a socket of its own,
+// for the replies of the peer to be told apart and sent back to it
+async fn netem_udp(listen: SocketAddr, peer: SocketAddr, netem: Netem, seed: u64) {
+    let socket = Arc::new(UdpSocket::bind(listen).await.unwrap());
+    let local: SocketAddr = if peer.is_ipv4() {
+        "0.0.0.0:0".parse().unwrap()
+    } else {
+        "[::]:0".parse().unwrap()
+    };
+    type Client = (Link, u64, Sender<(Instant, u64, Vec<u8>)>);
+    let mut clients: HashMap<SocketAddr, Client> = HashMap::new();
+    let mut buffer = vec![0u8; 65_536];
+    while let Ok((n, addr)) = socket.recv_from(&mut buffer).await {
+        if !clients.contains_key(&addr) {
+            let upstream = Arc::new(UdpSocket::bind(local).await.unwrap());
+            let seed = seed.wrapping_add(2 * clients.len() as u64);
+
+            let (to_peer, rx) = bounded(CHANNEL_CAPACITY);
+            task::spawn(deliver(rx, Output::Udp(upstream.clone(), peer)));
+            let (to_client, rx) = bounded(CHANNEL_CAPACITY);
+            task::spawn(deliver(rx, Output::Udp(socket.clone(), addr)));
+
+            task::spawn(async move {
+                let mut link = Link::new(netem, seed.wrapping_add(1), false);
+                let mut buffer = vec![0u8; 65_536];
+                let mut count: u64 = 0;
+                while let Ok((n, from)) = upstream.recv_from(&mut buffer).await {
+                    if from == peer {
+                        forward_datagram(&mut link, &mut count, &to_client, &buffer[..n]).await;
+                    }
+                }
+            });
+            clients.insert(addr, (Link::new(netem, seed, false), 0, to_peer));
+        }
+        let (link, count, to_peer) = clients.get_mut(&addr).unwrap();
+        forward_datagram(link, count, to_peer, &buffer[..n]).await;
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // initiate logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    let distribution = match opt.distribution.as_str() {
+        "uniform" => Distribution::Uniform,
+        "normal" => Distribution::Normal,
+        "pareto" => Distribution::Pareto,
+        _ => panic!("Unsupported distribution: {}", opt.distribution),
+    };
+    let bandwidth = if opt.bandwidth > 0.0 {
+        Some(opt.bandwidth * 1_000_000.0 / 8.0)
+    } else {
+        None
+    };
+    let netem = Netem {
+        delay: opt.delay,
+        jitter: opt.jitter,
+        distribution,
+        loss: opt.loss,
+        reorder: opt.reorder,
+        bandwidth,
+    };
+    let seed = opt.seed.unwrap_or_else(rand::random);
+
+    match opt.transport.as_str() {
+        "tcp" => {
+            // Dropping or reordering bytes would corrupt the stream, TCP
+            // recovers from loss below the proxy
+            if opt.loss > 0.0 || opt.reorder > 0.0 {
+                panic!("Unsupported loss or reordering on transport: tcp");
+            }
+            netem_tcp(opt.listen, opt.peer, netem, seed).await
+        }
+        "udp" => netem_udp(opt.listen, opt.peer, netem, seed).await,
+        _ => panic!("Unsupported transport: {}", opt.transport),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn netem() -> Netem {
+        Netem {
+            delay: 0.0,
+            jitter: 0.0,
+            distribution: Distribution::Uniform,
+            loss: 0.0,
+            reorder: 0.0,
+            bandwidth: None,
+        }
+    }
+
+    #[test]
+    fn delay() {
+        let mut link = Link::new(
+            Netem {
+                delay: 100.0,
+                ..netem()
+            },
+            0,
+            true,
+        );
+        let now = Instant::now();
+        let at = link.schedule(now, 1000).unwrap();
+        assert_eq!(at, now + Duration::from_millis(100));
+        assert_eq!(link.backlog(now), Duration::default());
+    }
+
+    #[test]
+    fn loss() {
+        let mut link = Link::new(
+            Netem {
+                loss: 100.0,
+                ..netem()
+            },
+            0,
+            false,
+        );
+        let now = Instant::now();
+        assert!((0..100).all(|_| link.schedule(now, 1000).is_none()));
+
+        let mut link = Link::new(
+            Netem {
+                loss: 50.0,
+                ..netem()
+            },
+            0,
+            false,
+        );
+        let lost = (0..1000)
+            .filter(|_| link.schedule(now, 1000).is_none())
+            .count();
+        assert!(lost > 400 && lost < 600);
+    }
+
+    #[test]
+    fn bandwidth() {
+        // 100 bytes take 100 ms to serialize
+        let mut link = Link::new(
+            Netem {
+                bandwidth: Some(1000.0),
+                ..netem()
+            },
+            0,
+            true,
+        );
+        let now = Instant::now();
+        let first = link.schedule(now, 100).unwrap();
+        let second = link.schedule(now, 100).unwrap();
+        assert_eq!(first, now + Duration::from_millis(100));
+        assert_eq!(second, now + Duration::from_millis(200));
+        assert_eq!(link.backlog(now), Duration::from_millis(200));
+        // The link is free again once it has serialized them
+        let later = now + Duration::from_millis(500);
+        assert_eq!(link.backlog(later), Duration::default());
+        assert_eq!(
+            link.schedule(later, 100).unwrap(),
+            later + Duration::from_millis(100)
+        );
+    }
+
+    #[test]
+    fn ordered_jitter() {
+        for distribution in &[
+            Distribution::Uniform,
+            Distribution::Normal,
+            Distribution::Pareto,
+        ] {
+            let netem = Netem {
+                delay: 10.0,
+                jitter: 10.0,
+                distribution: *distribution,
+                ..netem()
+            };
+            let now = Instant::now();
+            let mut link = Link::new(netem, 0, true);
+            let times: Vec<Instant> = (0..100).map(|_| link.schedule(now, 10).unwrap()).collect();
+            assert!(times.windows(2).all(|w| w[0] <= w[1]));
+
+            // The datagrams may be reordered by the jitter
+            let mut link = Link::new(netem, 0, false);
+            let times: Vec<Instant> = (0..100).map(|_| link.schedule(now, 10).unwrap()).collect();
+            assert!(times.windows(2).any(|w| w[0] > w[1]));
+        }
+    }
+
+    #[test]
+    fn reorder() {
+        let mut link = Link::new(
+            Netem {
+                delay: 1000.0,
+                reorder: 100.0,
+                ..netem()
+            },
+            0,
+            false,
+        );
+        let now = Instant::now();
+        assert_eq!(link.schedule(now, 10).unwrap(), now);
+    }
+
+    #[test]
+    fn seeded() {
+        let netem = Netem {
+            jitter: 10.0,
+            loss: 10.0,
+            ..netem()
+        };
+        let now = Instant::now();
+        let samples = |link: &mut Link| -> Vec<(bool, f64)> {
+            (0..100)
+                .map(|_| (link.schedule(now, 10).is_some(), link.jitter()))
+                .collect()
+        };
+        let (mut a, mut b) = (Link::new(netem, 42, false), Link::new(netem, 42, false));
+        assert_eq!(samples(&mut a), samples(&mut b));
+        let mut c = Link::new(netem, 43, false);
+        assert_ne!(samples(&mut a), samples(&mut c));
+    }
+}
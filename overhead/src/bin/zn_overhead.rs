@@ -19,6 +19,8 @@ use structopt::StructOpt;
 use zenoh::net::ResKey::*;
 use zenoh::net::*;
 use zenoh::Properties;
+use zenoh_perf_common::router;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "zn_overhead")]
@@ -35,6 +37,8 @@ struct Opt {
     total: u64,
     #[structopt(short = "i", long = "interval", default_value = "0")]
     interval: f64,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -51,6 +55,10 @@ async fn main() {
     config.insert("mode".to_string(), opt.mode.clone());
     config.insert("add_timestamp".to_string(), "false".to_string());
 
+    opt.tls.configure(&mut config);
+
+    router::configure(&opt.mode, &mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     config.insert("peer".to_string(), opt.locator);
 
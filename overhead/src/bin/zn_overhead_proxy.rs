@@ -0,0 +1,135 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use async_std::prelude::*;
+use async_std::sync::Arc;
+use async_std::task;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use structopt::StructOpt;
+use zenoh_perf_overhead::batch::{session_messages, Batches};
+use zenoh_perf_overhead::report::Stats;
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(name = "zn_overhead_proxy")]
+struct Opt {
+    #[structopt(short = "l", long = "listen", default_value = "127.0.0.1:7448")]
+    listen: SocketAddr,
+    #[structopt(short = "p", long = "peer", default_value = "127.0.0.1:7447")]
+    peer: SocketAddr,
+    #[structopt(short = "c", long = "csv")]
+    csv: bool,
+    #[structopt(short = "b", long = "batch-size", default_value = "65535")]
+    batch_size: usize,
+    #[structopt(short = "i", long = "interval", default_value = "1")]
+    interval: f64,
+}
+
+// Forward the bytes of one direction of a connection, decoding the framed
+// session messages as they go through
+async fn forward(mut src: TcpStream, mut dst: TcpStream, mut stats: Stats) -> Stats {
+    let mut buffer = vec![0u8; 65_536];
+    let mut batches = Batches::default();
+    loop {
+        let n = match src.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        if dst.write_all(&buffer[..n]).await.is_err() {
+            break;
+        }
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        stats.zenoh_size += n;
+
+        batches.extend(&buffer[..n]);
+        while let Some(batch) = batches.next_batch() {
+            for msg in session_messages(batch).iter() {
+                stats.add_session_message(timestamp, msg);
+            }
+        }
+    }
+    let _ = dst.shutdown(Shutdown::Write);
+    stats
+}
+
+// Forward both directions of a connection until it is closed and report its
+// overhead, the lock keeping the reports of the connections from being
+// interleaved
+async fn proxy(client: TcpStream, opt: Opt, print: Arc<Mutex<()>>) {
+    let peer = match TcpStream::connect(opt.peer).await {
+        Ok(peer) => peer,
+        Err(e) => {
+            println!("Failed to connect to {}: {}", opt.peer, e);
+            return;
+        }
+    };
+    let (src, dst) = (client.peer_addr().unwrap(), opt.peer);
+
+    let to_peer = task::spawn(forward(
+        client.clone(),
+        peer.clone(),
+        Stats::new(opt.batch_size, opt.interval),
+    ));
+    let to_client = task::spawn(forward(
+        peer,
+        client,
+        Stats::new(opt.batch_size, opt.interval),
+    ));
+    let flows = [(src, dst, to_peer.await), (dst, src, to_client.await)];
+
+    // The sizes on the wire are not visible from the proxy
+    let _print = print.lock().unwrap();
+    let mut total = Stats::default();
+    for (src, dst, stats) in flows.iter() {
+        if stats.zenoh_size == 0 {
+            continue;
+        }
+        if !opt.csv {
+            println!("Flow tcp/{} -> {}", src, dst);
+            stats.print();
+            println!();
+        }
+        total.add(stats);
+    }
+
+    if opt.csv {
+        total.print_csv();
+    } else {
+        println!("All flows of {}", src);
+        total.print();
+        println!();
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // initiate logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    if opt.batch_size == 0 || opt.interval <= 0.0 {
+        panic!("The batch size and the interval must be positive");
+    }
+
+    // Each connection is reported once closed
+    let listener = TcpListener::bind(opt.listen).await.unwrap();
+    let print = Arc::new(Mutex::new(()));
+    let mut incoming = listener.incoming();
+    while let Some(stream) = incoming.next().await {
+        let client = stream.unwrap();
+        task::spawn(proxy(client, opt.clone(), print.clone()));
+    }
+}
@@ -0,0 +1,322 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use async_std::prelude::*;
+use async_std::sync::Arc;
+use async_std::task;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use structopt::StructOpt;
+use zenoh::net::protocol::core::{ResKey, ZInt};
+use zenoh::net::protocol::proto::{
+    Declaration, Frame, FramePayload, SessionBody, SessionMessage, ZenohBody, ZenohMessage,
+};
+use zenoh_perf_overhead::batch::{session_messages, Batches};
+use zenoh_perf_overhead::report::{
+    session_kind, session_message_size, zenoh_kind, zenoh_message_size,
+};
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "zn_tap")]
+struct Opt {
+    #[structopt(short = "t", long = "transport", default_value = "tcp")]
+    transport: String,
+    #[structopt(short = "l", long = "listen", default_value = "127.0.0.1:7448")]
+    listen: SocketAddr,
+    #[structopt(short = "p", long = "peer", default_value = "127.0.0.1:7447")]
+    peer: SocketAddr,
+    #[structopt(short = "o", long = "output")]
+    output: Option<String>,
+    // Comma separated SessionMessage and ZenohMessage types, e.g. InitSyn,Data
+    #[structopt(long = "type")]
+    types: Option<String>,
+    // Only log the ZenohMessages on a key starting with this prefix
+    #[structopt(short = "k", long = "key")]
+    key: Option<String>,
+}
+
+// The key expressions declared by the sender of a direction, by resource id
+type Resources = HashMap<ZInt, String>;
+
+fn resolve(resources: &Resources, key: &ResKey) -> String {
+    let lookup = |rid: &ZInt| {
+        resources
+            .get(rid)
+            .cloned()
+            .unwrap_or_else(|| rid.to_string())
+    };
+    match key {
+        ResKey::RName(name) => name.clone(),
+        ResKey::RId(rid) => lookup(rid),
+        ResKey::RIdWithSuffix(rid, suffix) => format!("{}{}", lookup(rid), suffix),
+    }
+}
+
+// The key expressions of a ZenohMessage, the resource declarations being
+// recorded to resolve the later numerical keys
+fn keys(resources: &mut Resources, msg: &ZenohMessage) -> Vec<String> {
+    match &msg.body {
+        ZenohBody::Data(d) => vec![resolve(resources, &d.key)],
+        ZenohBody::Query(q) => vec![resolve(resources, &q.key)],
+        ZenohBody::Pull(p) => vec![resolve(resources, &p.key)],
+        ZenohBody::Declare(d) => {
+            let mut keys = vec![];
+            for decl in d.declarations.iter() {
+                let key = match decl {
+                    Declaration::Resource(r) => {
+                        let key = resolve(resources, &r.key);
+                        resources.insert(r.rid, key.clone());
+                        keys.push(key);
+                        continue;
+                    }
+                    Declaration::ForgetResource(_) => continue,
+                    Declaration::Publisher(p) => &p.key,
+                    Declaration::ForgetPublisher(p) => &p.key,
+                    Declaration::Subscriber(s) => &s.key,
+                    Declaration::ForgetSubscriber(s) => &s.key,
+                    Declaration::Queryable(q) => &q.key,
+                    Declaration::ForgetQueryable(q) => &q.key,
+                };
+                keys.push(resolve(resources, key));
+            }
+            keys
+        }
+        ZenohBody::Unit(_) | ZenohBody::LinkStateList(_) => vec![],
+    }
+}
+
+struct Tap {
+    types: Option<Vec<String>>,
+    key: Option<String>,
+    output: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Tap {
+    fn log(&self, line: serde_json::Value) {
+        let mut output = self.output.lock().unwrap();
+        let _ = writeln!(output, "{}", line);
+    }
+
+    fn accept_type(&self, kind: &str) -> bool {
+        match &self.types {
+            Some(types) => types.iter().any(|t| t == kind),
+            None => true,
+        }
+    }
+
+    fn accept_keys(&self, keys: &[String]) -> bool {
+        match &self.key {
+            Some(prefix) => keys.iter().any(|k| k.starts_with(prefix.as_str())),
+            None => true,
+        }
+    }
+
+    // Log a session message and the ZenohMessages it carries
+    fn session_message(
+        &self,
+        transport: &str,
+        src: SocketAddr,
+        dst: SocketAddr,
+        resources: &mut Resources,
+        msg: &SessionMessage,
+    ) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64();
+        let kind = session_kind(&msg.body);
+        // The session messages do not have any key
+        if self.accept_type(kind) && self.key.is_none() {
+            let mut line = json!({
+                "time": time,
+                "transport": transport,
+                "src": src.to_string(),
+                "dst": dst.to_string(),
+                "layer": "session",
+                "type": kind,
+                "size": session_message_size(msg),
+            });
+            if let SessionBody::Frame(f) = &msg.body {
+                line["sn"] = json!(f.sn);
+                match &f.payload {
+                    FramePayload::Messages { messages } => line["messages"] = json!(messages.len()),
+                    FramePayload::Fragment { is_final, .. } => {
+                        line["fragment"] = json!(true);
+                        line["final"] = json!(is_final);
+                    }
+                }
+            }
+            self.log(line);
+        }
+
+        if let SessionBody::Frame(Frame {
+            payload: FramePayload::Messages { messages },
+            ..
+        }) = &msg.body
+        {
+            for m in messages.iter() {
+                let kind = zenoh_kind(&m.body);
+                let keys = keys(resources, m);
+                if !self.accept_type(kind) || !self.accept_keys(&keys) {
+                    continue;
+                }
+                let mut line = json!({
+                    "time": time,
+                    "transport": transport,
+                    "src": src.to_string(),
+                    "dst": dst.to_string(),
+                    "layer": "zenoh",
+                    "type": kind,
+                    "size": zenoh_message_size(m),
+                    "keys": keys,
+                });
+                if let ZenohBody::Data(d) = &m.body {
+                    line["payload"] = json!(d.payload.len());
+                }
+                self.log(line);
+            }
+        }
+    }
+
+    fn decode(
+        &self,
+        transport: &str,
+        src: SocketAddr,
+        dst: SocketAddr,
+        resources: &mut Resources,
+        bytes: &[u8],
+    ) {
+        for msg in session_messages(bytes).iter() {
+            self.session_message(transport, src, dst, resources, msg);
+        }
+    }
+}
+
+// Forward the bytes of one direction of a TCP connection, logging the framed
+// session messages as they go through
+async fn forward(
+    mut from: TcpStream,
+    mut to: TcpStream,
+    src: SocketAddr,
+    dst: SocketAddr,
+    tap: Arc<Tap>,
+) {
+    let mut buffer = vec![0u8; 65_536];
+    let mut resources = Resources::new();
+    let mut batches = Batches::default();
+    loop {
+        let n = match from.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        if to.write_all(&buffer[..n]).await.is_err() {
+            break;
+        }
+
+        batches.extend(&buffer[..n]);
+        while let Some(batch) = batches.next_batch() {
+            tap.decode("tcp", src, dst, &mut resources, batch);
+        }
+    }
+    let _ = to.shutdown(Shutdown::Write);
+}
+
+async fn tap_tcp(listen: SocketAddr, peer: SocketAddr, tap: Arc<Tap>) {
+    let listener = TcpListener::bind(listen).await.unwrap();
+    let mut incoming = listener.incoming();
+    while let Some(stream) = incoming.next().await {
+        let client = stream.unwrap();
+        let upstream = TcpStream::connect(peer).await.unwrap();
+        let addr = client.peer_addr().unwrap();
+
+        task::spawn(forward(
+            client.clone(),
+            upstream.clone(),
+            addr,
+            peer,
+            tap.clone(),
+        ));
+        task::spawn(forward(upstream, client, peer, addr, tap.clone()));
+    }
+}
+
+// Forward the datagrams between each client and the peer through an upstream
+// socket of its own, for the replies to reach the right client, each datagram
+// carrying unframed session messages
+async fn tap_udp(listen: SocketAddr, peer: SocketAddr, tap: Arc<Tap>) {
+    let socket = Arc::new(UdpSocket::bind(listen).await.unwrap());
+    let local: SocketAddr = if peer.is_ipv4() {
+        "0.0.0.0:0".parse().unwrap()
+    } else {
+        "[::]:0".parse().unwrap()
+    };
+    // The upstream sockets and the key expressions declared, by client
+    let mut clients: HashMap<SocketAddr, (Arc<UdpSocket>, Resources)> = HashMap::new();
+
+    let mut buffer = vec![0u8; 65_536];
+    while let Ok((n, addr)) = socket.recv_from(&mut buffer).await {
+        if !clients.contains_key(&addr) {
+            let upstream = Arc::new(UdpSocket::bind(local).await.unwrap());
+            upstream.connect(peer).await.unwrap();
+
+            let c_socket = socket.clone();
+            let c_upstream = upstream.clone();
+            let c_tap = tap.clone();
+            task::spawn(async move {
+                let mut buffer = vec![0u8; 65_536];
+                let mut resources = Resources::new();
+                while let Ok(n) = c_upstream.recv(&mut buffer).await {
+                    let _ = c_socket.send_to(&buffer[..n], addr).await;
+                    c_tap.decode("udp", peer, addr, &mut resources, &buffer[..n]);
+                }
+            });
+            clients.insert(addr, (upstream, Resources::new()));
+        }
+        let (upstream, resources) = clients.get_mut(&addr).unwrap();
+        let _ = upstream.send(&buffer[..n]).await;
+        tap.decode("udp", addr, peer, resources, &buffer[..n]);
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // initiate logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    let output: Box<dyn Write + Send> = match &opt.output {
+        Some(file) => Box::new(LineWriter::new(File::create(file).unwrap())),
+        None => Box::new(io::stdout()),
+    };
+    let tap = Arc::new(Tap {
+        types: opt
+            .types
+            .as_ref()
+            .map(|t| t.split(',').map(|s| s.to_string()).collect()),
+        key: opt.key.clone(),
+        output: Mutex::new(output),
+    });
+
+    match opt.transport.as_str() {
+        "tcp" => tap_tcp(opt.listen, opt.peer, tap).await,
+        "udp" => tap_udp(opt.listen, opt.peer, tap).await,
+        _ => panic!("Unsupported transport: {}", opt.transport),
+    }
+}
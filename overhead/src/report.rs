@@ -0,0 +1,361 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use std::collections::BTreeMap;
+use std::time::Duration;
+use zenoh::net::protocol::io::WBuf;
+use zenoh::net::protocol::proto::{
+    Frame, FramePayload, SessionBody, SessionMessage, ZenohBody, ZenohMessage,
+};
+
+pub fn session_kind(body: &SessionBody) -> &'static str {
+    match body {
+        SessionBody::Scout(_) => "Scout",
+        SessionBody::Hello(_) => "Hello",
+        SessionBody::InitSyn(_) => "InitSyn",
+        SessionBody::InitAck(_) => "InitAck",
+        SessionBody::OpenSyn(_) => "OpenSyn",
+        SessionBody::OpenAck(_) => "OpenAck",
+        SessionBody::Close(_) => "Close",
+        SessionBody::Sync(_) => "Sync",
+        SessionBody::AckNack(_) => "AckNack",
+        SessionBody::KeepAlive(_) => "KeepAlive",
+        SessionBody::Ping(_) => "Ping",
+        SessionBody::Pong(_) => "Pong",
+        SessionBody::Frame(_) => "Frame",
+    }
+}
+
+pub fn zenoh_kind(body: &ZenohBody) -> &'static str {
+    match body {
+        ZenohBody::Data(_) => "Data",
+        ZenohBody::Declare(_) => "Declare",
+        ZenohBody::Query(_) => "Query",
+        ZenohBody::Pull(_) => "Pull",
+        ZenohBody::Unit(_) => "Unit",
+        ZenohBody::LinkStateList(_) => "LinkStateList",
+    }
+}
+
+// The encoded size of the messages, as they were serialized on the wire, or
+// None if they cannot be encoded back
+pub fn session_message_size(msg: &SessionMessage) -> Option<usize> {
+    let mut wbuf = WBuf::new(64, false);
+    if wbuf.write_session_message(msg) {
+        Some(wbuf.len())
+    } else {
+        None
+    }
+}
+
+pub fn zenoh_message_size(msg: &ZenohMessage) -> Option<usize> {
+    let mut msg = msg.clone();
+    let mut wbuf = WBuf::new(64, false);
+    if wbuf.write_zenoh_message(&mut msg) {
+        Some(wbuf.len())
+    } else {
+        None
+    }
+}
+
+#[derive(Default)]
+struct Kind {
+    count: usize,
+    size: usize,
+}
+
+// The data sent during an interval of the capture
+#[derive(Default)]
+struct Interval {
+    wire_size: usize,
+    data_size: usize,
+    data_count: usize,
+    payload_size: usize,
+}
+
+#[derive(Default)]
+pub struct Stats {
+    pub wire_size: usize,
+    pub ip_size: usize,
+    pub zenoh_size: usize,
+    pub gaps: usize,
+    pub missing: usize,
+    // The bytes that could not be decoded, following a gap
+    pub skipped: usize,
+    pub retransmitted: usize,
+    session_messages: usize,
+    zenoh_messages: usize,
+    // The messages that could not be encoded back to measure their size
+    unencoded: usize,
+    // The size of the session messages carrying data
+    data_size: usize,
+    data_count: usize,
+    payload_size: usize,
+    session_kinds: BTreeMap<&'static str, Kind>,
+    zenoh_kinds: BTreeMap<&'static str, Kind>,
+    // The number of frames per count of ZenohMessages they carry
+    batching: BTreeMap<usize, usize>,
+    // The number of frames per tenth of the batch size they fill
+    filling: BTreeMap<usize, usize>,
+    batch_size: usize,
+    // The data sent per interval, keyed by the interval index since the epoch
+    intervals: BTreeMap<u64, Interval>,
+    interval: f64,
+}
+
+impl Stats {
+    pub fn new(batch_size: usize, interval: f64) -> Self {
+        Self {
+            batch_size,
+            interval,
+            ..Default::default()
+        }
+    }
+
+    // Account a packet captured on the wire at the given time
+    pub fn add_packet(&mut self, timestamp: Duration, frame_len: usize) {
+        self.interval(timestamp).wire_size += frame_len;
+    }
+
+    // Account a session message sent at the given time
+    pub fn add_session_message(&mut self, timestamp: Duration, m: &SessionMessage) {
+        self.session_messages += 1;
+        let size = match session_message_size(m) {
+            Some(size) => size,
+            None => {
+                self.unencoded += 1;
+                return;
+            }
+        };
+        let kind = self.session_kinds.entry(session_kind(&m.body)).or_default();
+        kind.count += 1;
+        kind.size += size;
+
+        let messages = match &m.body {
+            SessionBody::Frame(Frame {
+                payload: FramePayload::Messages { messages },
+                ..
+            }) => messages,
+            _ => return,
+        };
+        *self.batching.entry(messages.len()).or_default() += 1;
+        *self.filling.entry(size * 10 / self.batch_size).or_default() += 1;
+
+        let mut data = false;
+        for z in messages.iter() {
+            self.zenoh_messages += 1;
+            let kind = self.zenoh_kinds.entry(zenoh_kind(&z.body)).or_default();
+            kind.count += 1;
+            match zenoh_message_size(z) {
+                Some(size) => kind.size += size,
+                None => self.unencoded += 1,
+            }
+            if let ZenohBody::Data(d) = &z.body {
+                data = true;
+                self.data_count += 1;
+                self.payload_size += d.payload.len();
+                let i = self.interval(timestamp);
+                i.data_count += 1;
+                i.payload_size += d.payload.len();
+            }
+        }
+        if data {
+            self.data_size += size;
+            self.interval(timestamp).data_size += size;
+        }
+    }
+
+    pub fn add(&mut self, other: &Stats) {
+        self.wire_size += other.wire_size;
+        self.ip_size += other.ip_size;
+        self.zenoh_size += other.zenoh_size;
+        self.gaps += other.gaps;
+        self.missing += other.missing;
+        self.skipped += other.skipped;
+        self.retransmitted += other.retransmitted;
+        self.session_messages += other.session_messages;
+        self.zenoh_messages += other.zenoh_messages;
+        self.unencoded += other.unencoded;
+        self.data_size += other.data_size;
+        self.data_count += other.data_count;
+        self.payload_size += other.payload_size;
+        for (name, k) in other.session_kinds.iter() {
+            let kind = self.session_kinds.entry(name).or_default();
+            kind.count += k.count;
+            kind.size += k.size;
+        }
+        for (name, k) in other.zenoh_kinds.iter() {
+            let kind = self.zenoh_kinds.entry(name).or_default();
+            kind.count += k.count;
+            kind.size += k.size;
+        }
+        for (n, count) in other.batching.iter() {
+            *self.batching.entry(*n).or_default() += count;
+        }
+        for (tenth, count) in other.filling.iter() {
+            *self.filling.entry(*tenth).or_default() += count;
+        }
+        self.batch_size = other.batch_size;
+        for (index, i) in other.intervals.iter() {
+            let interval = self.intervals.entry(*index).or_default();
+            interval.wire_size += i.wire_size;
+            interval.data_size += i.data_size;
+            interval.data_count += i.data_count;
+            interval.payload_size += i.payload_size;
+        }
+        self.interval = other.interval;
+    }
+
+    fn interval(&mut self, timestamp: Duration) -> &mut Interval {
+        let index = (timestamp.as_secs_f64() / self.interval).floor() as u64;
+        self.intervals.entry(index).or_default()
+    }
+
+    // The bytes carried by TCP or UDP, including the retransmitted ones
+    fn transport_size(&self) -> usize {
+        self.zenoh_size + self.retransmitted
+    }
+
+    fn payload(&self) -> usize {
+        if self.data_count > 0 {
+            self.payload_size / self.data_count
+        } else {
+            0
+        }
+    }
+
+    // Whether the sizes on the wire are known, they are not without a capture
+    fn captured(&self) -> bool {
+        self.wire_size > 0
+    }
+
+    pub fn print(&self) {
+        if self.captured() {
+            println!("Total Size on the wire: {} bytes", self.wire_size);
+            println!("Total Size of IP packets: {} bytes", self.ip_size);
+        }
+        println!(
+            "Total Size of transport payloads: {} bytes",
+            self.transport_size()
+        );
+        println!("Total Size of Zenoh messages: {} bytes", self.zenoh_size);
+        println!(
+            "Gaps: {} ({} bytes missing, {} bytes skipped), {} bytes retransmitted",
+            self.gaps, self.missing, self.skipped, self.retransmitted
+        );
+        println!("Total SessionMessages: {}", self.session_messages);
+        for (name, kind) in self.session_kinds.iter() {
+            println!("\t{}: {} ({} bytes)", name, kind.count, kind.size);
+        }
+        println!("Total Zenoh Messages: {}", self.zenoh_messages);
+        for (name, kind) in self.zenoh_kinds.iter() {
+            println!("\t{}: {} ({} bytes)", name, kind.count, kind.size);
+        }
+        if self.unencoded > 0 {
+            println!("Messages left unsized: {}", self.unencoded);
+        }
+        println!("Total Data messages: {}", self.data_count);
+        println!("Total Payload: {} bytes", self.payload_size);
+        if self.data_count > 0 {
+            println!("Per message Payload: {} bytes", self.payload());
+            // Everything that is not payload is accounted as overhead
+            let overhead = |size: usize| {
+                size.saturating_sub(self.payload_size) as f64 / self.data_count as f64
+            };
+            if self.captured() {
+                println!(
+                    "Per message Overhead: wire {:.2}, IP {:.2}, transport {:.2}, Zenoh {:.2} bytes",
+                    overhead(self.wire_size),
+                    overhead(self.ip_size),
+                    overhead(self.transport_size()),
+                    overhead(self.zenoh_size)
+                );
+            } else {
+                println!(
+                    "Per message Overhead: transport {:.2}, Zenoh {:.2} bytes",
+                    overhead(self.transport_size()),
+                    overhead(self.zenoh_size)
+                );
+            }
+        }
+        self.print_batching();
+    }
+
+    fn print_batching(&self) {
+        if self.batching.is_empty() {
+            return;
+        }
+        println!("Zenoh Messages per Frame:");
+        for (n, count) in self.batching.iter() {
+            println!("\t{}: {} frames", n, count);
+        }
+        println!(
+            "Frame size relative to a batch size of {}:",
+            self.batch_size
+        );
+        for (tenth, count) in self.filling.iter() {
+            if *tenth < 10 {
+                println!("\t{}-{}%: {} frames", tenth * 10, (tenth + 1) * 10, count);
+            } else {
+                println!("\t100%+: {} frames", count);
+            }
+        }
+        println!("Per message Overhead by publish rate:");
+        let start = match self.intervals.keys().next() {
+            Some(start) => *start,
+            None => return,
+        };
+        for (index, i) in self.intervals.iter() {
+            if i.data_count == 0 {
+                continue;
+            }
+            let overhead =
+                |size: usize| size.saturating_sub(i.payload_size) as f64 / i.data_count as f64;
+            let time = (index - start) as f64 * self.interval;
+            let rate = i.data_count as f64 / self.interval;
+            if self.captured() {
+                println!(
+                    "\t{:.3}s: {:.0} msg/s, wire {:.2}, Zenoh {:.2} bytes",
+                    time,
+                    rate,
+                    overhead(i.wire_size),
+                    overhead(i.data_size)
+                );
+            } else {
+                println!(
+                    "\t{:.3}s: {:.0} msg/s, Zenoh {:.2} bytes",
+                    time,
+                    rate,
+                    overhead(i.data_size)
+                );
+            }
+        }
+    }
+
+    // Same schema as the MQTT analyzer in comparison/mqtt/overhead
+    pub fn print_csv(&self) {
+        println!(
+            "protocol,total_wire,total_ip,total_tcp,total_mqtt,data_mqtt,total_payload,payload"
+        );
+        println!(
+            "zenoh,{},{},{},{},{},{},{}",
+            self.wire_size,
+            self.ip_size,
+            self.transport_size(),
+            self.zenoh_size,
+            self.data_size,
+            self.payload_size,
+            self.payload()
+        );
+    }
+}
@@ -25,6 +25,7 @@ use zenoh::net::protocol::session::Primitives;
 use zenoh::net::queryable::ALL_KINDS;
 use zenoh::net::routing::face::Face;
 use zenoh::net::runtime::Runtime;
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::properties::config::{
     ConfigProperties, ZN_LISTENER_KEY, ZN_MODE_KEY, ZN_MULTICAST_SCOUTING_KEY, ZN_PEER_KEY,
 };
@@ -134,6 +135,8 @@ struct Opt {
     mode: String,
     #[structopt(short = "p", long = "payload")]
     payload: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -147,6 +150,8 @@ async fn main() {
     let mut config = ConfigProperties::default();
     config.insert(ZN_MODE_KEY, opt.mode.clone());
 
+    opt.tls.configure_runtime(&mut config);
+
     config.insert(ZN_MULTICAST_SCOUTING_KEY, "false".to_string());
     match opt.mode.as_str() {
         "peer" | "router" => config.insert(ZN_LISTENER_KEY, opt.locator),
@@ -13,7 +13,6 @@
 //
 use std::collections::HashMap;
 use std::sync::{Arc, Barrier, Mutex};
-use std::time::Instant;
 use structopt::StructOpt;
 use zenoh::net::protocol::core::{
     Channel, PeerId, QueryConsolidation, QueryTarget, ResKey, SubInfo, ZInt,
@@ -22,23 +21,32 @@ use zenoh::net::protocol::io::ZBuf;
 use zenoh::net::protocol::proto::{DataInfo, RoutingContext};
 use zenoh::net::protocol::session::Primitives;
 use zenoh::net::runtime::Runtime;
+use zenoh_perf_common::router;
+use zenoh_perf_query::latency::{self, Pending, PendingQuery};
+use zenoh_perf_query::predicate::make_predicate;
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::properties::config::{
     ConfigProperties, ZN_MODE_KEY, ZN_MULTICAST_SCOUTING_KEY, ZN_PEER_KEY,
 };
 
-type Pending = Arc<Mutex<HashMap<u64, (Instant, Arc<Barrier>)>>>;
-
 struct QueryPrimitives {
     scenario: String,
     name: String,
+    predicate_size: usize,
     pending: Pending,
 }
 
 impl QueryPrimitives {
-    pub fn new(scenario: String, name: String, pending: Pending) -> QueryPrimitives {
+    pub fn new(
+        scenario: String,
+        name: String,
+        predicate_size: usize,
+        pending: Pending,
+    ) -> QueryPrimitives {
         QueryPrimitives {
             scenario,
             name,
+            predicate_size,
             pending,
         }
     }
@@ -94,19 +102,33 @@ impl Primitives for QueryPrimitives {
         _info: Option<DataInfo>,
         payload: ZBuf,
     ) {
-        let tuple = self.pending.lock().unwrap().remove(&qid).unwrap();
-        let (instant, barrier) = (tuple.0, tuple.1);
-        barrier.wait();
-        println!(
-            "router,{},query.latency,{},{},{},{}",
-            self.scenario,
-            self.name,
-            payload.len(),
+        // The replies of the queries already finalised are ignored
+        match self.pending.lock().unwrap().get_mut(&qid) {
+            Some(query) => query.reply(payload.len()),
+            None => log::warn!("Reply data for unknown query: {}", qid),
+        }
+    }
+    fn send_reply_final(&self, qid: ZInt) {
+        let query = match self.pending.lock().unwrap().remove(&qid) {
+            Some(query) => query,
+            None => {
+                log::warn!("Final reply for unknown query: {}", qid);
+                return;
+            }
+        };
+        let elapsed = query.instant.elapsed().as_micros();
+        query.barrier.wait();
+
+        latency::print(
+            "router",
+            &self.scenario,
+            &self.name,
+            self.predicate_size,
             qid,
-            instant.elapsed().as_micros()
+            &query.replies,
+            elapsed,
         );
     }
-    fn send_reply_final(&self, _qid: ZInt) {}
     fn send_pull(
         &self,
         _is_final: bool,
@@ -129,6 +151,10 @@ struct Opt {
     name: String,
     #[structopt(short = "s", long = "scenario")]
     scenario: String,
+    #[structopt(long = "predicate-size", default_value = "0")]
+    predicate_size: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -142,15 +168,21 @@ async fn main() {
     let mut config = ConfigProperties::default();
     config.insert(ZN_MODE_KEY, opt.mode.clone());
 
+    opt.tls.configure_runtime(&mut config);
+
+    router::configure_runtime(&opt.mode, &mut config);
+
     config.insert(ZN_MULTICAST_SCOUTING_KEY, "false".to_string());
     config.insert(ZN_PEER_KEY, opt.locator);
 
     let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+    let predicate = make_predicate(opt.predicate_size);
 
     let runtime = Runtime::new(0u8, config, None).await.unwrap();
     let rx_primitives = Arc::new(QueryPrimitives::new(
         opt.scenario,
         opt.name,
+        predicate.len(),
         pending.clone(),
     ));
     let tx_primitives = runtime.router.new_primitives(rx_primitives);
@@ -159,26 +191,23 @@ async fn main() {
     let mut count: u64 = 0;
     loop {
         let reskey = ResKey::RName("/test/query".to_string());
-        let predicate = "";
         let qid = count;
         let target = QueryTarget::default();
         let consolidation = QueryConsolidation::default();
         let routing_context = None;
 
         // Insert the pending query
-        pending
-            .lock()
-            .unwrap()
-            .insert(count, (Instant::now(), barrier.clone()));
+        let query = PendingQuery::new(barrier.clone());
+        pending.lock().unwrap().insert(count, query);
         tx_primitives.send_query(
             &reskey,
-            predicate,
+            &predicate,
             qid,
             target.clone(),
             consolidation.clone(),
             routing_context,
         );
-        // Wait for the reply to arrive
+        // Wait for the final reply to arrive
         barrier.wait();
 
         count += 1;
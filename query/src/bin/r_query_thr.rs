@@ -13,7 +13,6 @@
 //
 use async_std::task;
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Barrier, Mutex};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
@@ -24,19 +23,22 @@ use zenoh::net::protocol::io::ZBuf;
 use zenoh::net::protocol::proto::{DataInfo, RoutingContext};
 use zenoh::net::protocol::session::Primitives;
 use zenoh::net::runtime::Runtime;
+use zenoh_perf_common::router;
+use zenoh_perf_query::latency::{self, Breakdown, Pending, PendingQuery};
+use zenoh_perf_query::predicate::make_predicate;
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::properties::config::{
     ConfigProperties, ZN_MODE_KEY, ZN_MULTICAST_SCOUTING_KEY, ZN_PEER_KEY,
 };
 
-type Pending = Arc<Mutex<HashMap<u64, Arc<Barrier>>>>;
-
 struct QueryPrimitives {
     pending: Pending,
+    breakdown: Arc<Breakdown>,
 }
 
 impl QueryPrimitives {
-    pub fn new(pending: Pending) -> QueryPrimitives {
-        QueryPrimitives { pending }
+    pub fn new(pending: Pending, breakdown: Arc<Breakdown>) -> QueryPrimitives {
+        QueryPrimitives { pending, breakdown }
     }
 }
 
@@ -88,12 +90,27 @@ impl Primitives for QueryPrimitives {
         _replier_id: PeerId,
         _reskey: ResKey,
         _info: Option<DataInfo>,
-        _payload: ZBuf,
+        payload: ZBuf,
     ) {
-        let barrier = self.pending.lock().unwrap().remove(&qid).unwrap();
-        barrier.wait();
+        // The replies of the queries already finalised are ignored
+        match self.pending.lock().unwrap().get_mut(&qid) {
+            Some(query) => query.reply(payload.len()),
+            None => log::warn!("Reply data for unknown query: {}", qid),
+        }
+    }
+    fn send_reply_final(&self, qid: ZInt) {
+        let query = match self.pending.lock().unwrap().remove(&qid) {
+            Some(query) => query,
+            None => {
+                log::warn!("Final reply for unknown query: {}", qid);
+                return;
+            }
+        };
+        let elapsed = query.instant.elapsed().as_micros();
+        self.breakdown
+            .add(query.replies.iter().map(|(_, time)| *time), elapsed);
+        query.barrier.wait();
     }
-    fn send_reply_final(&self, _qid: ZInt) {}
     fn send_pull(
         &self,
         _is_final: bool,
@@ -118,6 +135,10 @@ struct Opt {
     scenario: String,
     #[structopt(short = "p", long = "payload")]
     payload: usize,
+    #[structopt(long = "predicate-size", default_value = "0")]
+    predicate_size: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -131,36 +152,51 @@ async fn main() {
     let mut config = ConfigProperties::default();
     config.insert(ZN_MODE_KEY, opt.mode.clone());
 
+    opt.tls.configure_runtime(&mut config);
+
+    router::configure_runtime(&opt.mode, &mut config);
+
     config.insert(ZN_MULTICAST_SCOUTING_KEY, "false".to_string());
     config.insert(ZN_PEER_KEY, opt.locator.clone());
 
-    let rtt = Arc::new(AtomicUsize::new(0));
-    let counter = Arc::new(AtomicUsize::new(0));
+    let breakdown = Arc::new(Breakdown::default());
     let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+    let predicate = make_predicate(opt.predicate_size);
+    let predicate_size = predicate.len();
 
     let runtime = Runtime::new(0u8, config, None).await.unwrap();
-    let rx_primitives = Arc::new(QueryPrimitives::new(pending.clone()));
+    let rx_primitives = Arc::new(QueryPrimitives::new(pending.clone(), breakdown.clone()));
     let tx_primitives = runtime.router.new_primitives(rx_primitives);
 
-    let c_rtt = rtt.clone();
-    let c_counter = counter.clone();
     task::spawn(async move {
         loop {
             let now = Instant::now();
             task::sleep(Duration::from_secs(1)).await;
             let elapsed = now.elapsed().as_micros() as f64;
 
-            let r = c_rtt.swap(0, Ordering::Relaxed);
-            let c = c_counter.swap(0, Ordering::Relaxed);
+            let sums = breakdown.take();
+            let c = sums.queries;
             if c > 0 {
                 let interval = 1_000_000.0 / elapsed;
                 println!(
-                    "router,{},query.throughput,{},{},{},{}",
+                    "router,{},query.throughput,{},{},{},{},{}",
                     opt.scenario,
                     opt.name,
                     opt.payload,
                     (c as f64 / interval).floor() as usize,
-                    (r as f64 / c as f64).floor() as usize,
+                    (sums.elapsed as f64 / c as f64).floor() as usize,
+                    predicate_size,
+                );
+                println!(
+                    "{}",
+                    latency::breakdown(
+                        "router",
+                        &opt.scenario,
+                        &opt.name,
+                        opt.payload,
+                        predicate_size,
+                        &sums
+                    )
                 );
             }
         }
@@ -169,7 +205,6 @@ async fn main() {
     let mut count: u64 = 0;
     loop {
         let reskey = ResKey::RName("/test/query".to_string());
-        let predicate = "";
         let qid = count;
         let target = QueryTarget::default();
         let consolidation = QueryConsolidation::default();
@@ -177,20 +212,18 @@ async fn main() {
 
         // Insert the pending query
         let barrier = Arc::new(Barrier::new(2));
-        pending.lock().unwrap().insert(count, barrier.clone());
-        let now = Instant::now();
+        let query = PendingQuery::new(barrier.clone());
+        pending.lock().unwrap().insert(count, query);
         tx_primitives.send_query(
             &reskey,
-            predicate,
+            &predicate,
             qid,
             target.clone(),
             consolidation.clone(),
             routing_context,
         );
-        // Wait for the reply to arrive
+        // Wait for the final reply to arrive
         barrier.wait();
-        rtt.fetch_add(now.elapsed().as_micros() as usize, Ordering::Relaxed);
-        counter.fetch_add(1, Ordering::Relaxed);
 
         count += 1;
     }
@@ -23,6 +23,7 @@ use zenoh::net::protocol::proto::{Query, ReplyContext, ZenohBody, ZenohMessage};
 use zenoh::net::protocol::session::{
     Session, SessionEventHandler, SessionHandler, SessionManager, SessionManagerConfig,
 };
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::core::ZResult;
 
 // Session Handler for the peer
@@ -79,6 +80,12 @@ impl SessionEventHandler for MyMH {
                     reply_context,
                     attachment,
                 );
+                self.session.handle_message(message)?;
+
+                // Signal the final reply
+                let reply_context = Some(ReplyContext { qid, replier: None });
+                let attachment = None;
+                let message = ZenohMessage::make_unit(channel, reply_context, attachment);
 
                 self.session.handle_message(message)
             }
@@ -104,6 +111,8 @@ struct Opt {
     mode: String,
     #[structopt(short = "p", long = "payload")]
     payload: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -117,6 +126,7 @@ async fn main() {
     let whatami = match opt.mode.as_str() {
         "peer" => whatami::PEER,
         "client" => whatami::CLIENT,
+        "router" => whatami::ROUTER,
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
 
@@ -125,16 +135,18 @@ async fn main() {
     rand::thread_rng().fill_bytes(&mut pid);
     let pid = PeerId::new(1, pid);
 
+    let opt_config = opt.tls.manager_config().await;
+
     let config = SessionManagerConfig {
         version: 0,
         whatami,
         id: pid,
         handler: Arc::new(MySH::new(opt.payload)),
     };
-    let manager = SessionManager::new(config, None);
+    let manager = SessionManager::new(config, opt_config);
 
     // Connect to the peer or listen
-    if whatami == whatami::PEER {
+    if whatami != whatami::CLIENT {
         manager.add_listener(&opt.locator).await.unwrap();
     } else {
         let _session = manager.open_session(&opt.locator).await.unwrap();
@@ -15,30 +15,32 @@ use rand::RngCore;
 use std::any::Any;
 use std::collections::HashMap;
 use std::sync::{Arc, Barrier, Mutex};
-use std::time::Instant;
 use structopt::StructOpt;
 use zenoh::net::protocol::core::{whatami, PeerId, QueryConsolidation, QueryTarget, ResKey};
 use zenoh::net::protocol::link::{Link, Locator};
-use zenoh::net::protocol::proto::{Data, ZenohBody, ZenohMessage};
+use zenoh::net::protocol::proto::{Data, Unit, ZenohBody, ZenohMessage};
 use zenoh::net::protocol::session::{
     Session, SessionEventHandler, SessionHandler, SessionManager, SessionManagerConfig,
 };
+use zenoh_perf_query::latency::{self, Pending, PendingQuery};
+use zenoh_perf_query::predicate::make_predicate;
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::core::ZResult;
 
-type Pending = Arc<Mutex<HashMap<u64, (Instant, Arc<Barrier>)>>>;
-
 // Session Handler for the blocking locator
 struct MySH {
     scenario: String,
     name: String,
+    predicate_size: usize,
     pending: Pending,
 }
 
 impl MySH {
-    fn new(scenario: String, name: String, pending: Pending) -> Self {
+    fn new(scenario: String, name: String, predicate_size: usize, pending: Pending) -> Self {
         Self {
             scenario,
             name,
+            predicate_size,
             pending,
         }
     }
@@ -52,6 +54,7 @@ impl SessionHandler for MySH {
         Ok(Arc::new(MyMH::new(
             self.scenario.clone(),
             self.name.clone(),
+            self.predicate_size,
             self.pending.clone(),
         )))
     }
@@ -61,14 +64,16 @@ impl SessionHandler for MySH {
 struct MyMH {
     scenario: String,
     name: String,
+    predicate_size: usize,
     pending: Pending,
 }
 
 impl MyMH {
-    fn new(scenario: String, name: String, pending: Pending) -> Self {
+    fn new(scenario: String, name: String, predicate_size: usize, pending: Pending) -> Self {
         Self {
             scenario,
             name,
+            predicate_size,
             pending,
         }
     }
@@ -82,22 +87,34 @@ impl SessionEventHandler for MyMH {
                 reply_context,
                 ..
             }) => {
-                let reply_context = reply_context.unwrap();
-                let tuple = self
-                    .pending
-                    .lock()
-                    .unwrap()
-                    .remove(&reply_context.qid)
-                    .unwrap();
-                let (instant, barrier) = (tuple.0, tuple.1);
-                barrier.wait();
-                println!(
-                    "session,{},query.latency,{},{},{},{}",
-                    self.scenario,
-                    self.name,
-                    payload.len(),
-                    reply_context.qid,
-                    instant.elapsed().as_micros()
+                // The replies of the queries already finalised are ignored
+                let qid = reply_context.unwrap().qid;
+                match self.pending.lock().unwrap().get_mut(&qid) {
+                    Some(query) => query.reply(payload.len()),
+                    None => log::warn!("Reply data for unknown query: {}", qid),
+                }
+            }
+            ZenohBody::Unit(Unit { reply_context, .. }) => {
+                // The final reply is signaled by a Unit message
+                let qid = reply_context.unwrap().qid;
+                let query = match self.pending.lock().unwrap().remove(&qid) {
+                    Some(query) => query,
+                    None => {
+                        log::warn!("Final reply for unknown query: {}", qid);
+                        return Ok(());
+                    }
+                };
+                let elapsed = query.instant.elapsed().as_micros();
+                query.barrier.wait();
+
+                latency::print(
+                    "session",
+                    &self.scenario,
+                    &self.name,
+                    self.predicate_size,
+                    qid,
+                    &query.replies,
+                    elapsed,
                 );
             }
             _ => panic!("Invalid message"),
@@ -125,6 +142,10 @@ struct Opt {
     name: String,
     #[structopt(short = "s", long = "scenario")]
     scenario: String,
+    #[structopt(long = "predicate-size", default_value = "0")]
+    predicate_size: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -138,6 +159,7 @@ async fn main() {
     let whatami = match opt.mode.as_str() {
         "peer" => whatami::PEER,
         "client" => whatami::CLIENT,
+        "router" => whatami::ROUTER,
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
 
@@ -147,6 +169,8 @@ async fn main() {
     let pid = PeerId::new(1, pid);
 
     let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+    let predicate = make_predicate(opt.predicate_size);
+    let opt_config = opt.tls.manager_config().await;
     let config = SessionManagerConfig {
         version: 0,
         whatami,
@@ -154,10 +178,11 @@ async fn main() {
         handler: Arc::new(MySH::new(
             opt.scenario.clone(),
             opt.name.clone(),
+            predicate.len(),
             pending.clone(),
         )),
     };
-    let manager = SessionManager::new(config, None);
+    let manager = SessionManager::new(config, opt_config);
 
     // Connect to publisher
     let session = manager.open_session(&opt.locator).await.unwrap();
@@ -166,7 +191,6 @@ async fn main() {
     loop {
         // Create and send the message
         let key = ResKey::RName("/test/query".to_string());
-        let predicate = "".to_string();
         let qid = count;
         let target = Some(QueryTarget::default());
         let consolidation = QueryConsolidation::default();
@@ -175,7 +199,7 @@ async fn main() {
 
         let message = ZenohMessage::make_query(
             key,
-            predicate,
+            predicate.clone(),
             qid,
             target,
             consolidation,
@@ -184,12 +208,10 @@ async fn main() {
         );
 
         // Insert the pending query
-        pending
-            .lock()
-            .unwrap()
-            .insert(count, (Instant::now(), barrier.clone()));
+        let query = PendingQuery::new(barrier.clone());
+        pending.lock().unwrap().insert(count, query);
         session.handle_message(message).unwrap();
-        // Wait for the reply to arrive
+        // Wait for the final reply to arrive
         barrier.wait();
 
         count += 1;
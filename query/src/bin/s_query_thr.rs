@@ -15,28 +15,29 @@ use async_std::task;
 use rand::RngCore;
 use std::any::Any;
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Barrier, Mutex};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 use zenoh::net::protocol::core::{whatami, PeerId, QueryConsolidation, QueryTarget, ResKey};
 use zenoh::net::protocol::link::{Link, Locator};
-use zenoh::net::protocol::proto::{Data, ZenohBody, ZenohMessage};
+use zenoh::net::protocol::proto::{Data, Unit, ZenohBody, ZenohMessage};
 use zenoh::net::protocol::session::{
     Session, SessionEventHandler, SessionHandler, SessionManager, SessionManagerConfig,
 };
+use zenoh_perf_query::latency::{self, Breakdown, Pending, PendingQuery};
+use zenoh_perf_query::predicate::make_predicate;
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::core::ZResult;
 
-type Pending = Arc<Mutex<HashMap<u64, Arc<Barrier>>>>;
-
 // Session Handler for the blocking locator
 struct MySH {
     pending: Pending,
+    breakdown: Arc<Breakdown>,
 }
 
 impl MySH {
-    fn new(pending: Pending) -> Self {
-        Self { pending }
+    fn new(pending: Pending, breakdown: Arc<Breakdown>) -> Self {
+        Self { pending, breakdown }
     }
 }
 
@@ -45,33 +46,54 @@ impl SessionHandler for MySH {
         &self,
         _session: Session,
     ) -> ZResult<Arc<dyn SessionEventHandler + Send + Sync>> {
-        Ok(Arc::new(MyMH::new(self.pending.clone())))
+        Ok(Arc::new(MyMH::new(
+            self.pending.clone(),
+            self.breakdown.clone(),
+        )))
     }
 }
 
 // Message Handler for the locator
 struct MyMH {
     pending: Pending,
+    breakdown: Arc<Breakdown>,
 }
 
 impl MyMH {
-    fn new(pending: Pending) -> Self {
-        Self { pending }
+    fn new(pending: Pending, breakdown: Arc<Breakdown>) -> Self {
+        Self { pending, breakdown }
     }
 }
 
 impl SessionEventHandler for MyMH {
     fn handle_message(&self, message: ZenohMessage) -> ZResult<()> {
         match message.body {
-            ZenohBody::Data(Data { reply_context, .. }) => {
-                let reply_context = reply_context.unwrap();
-                let barrier = self
-                    .pending
-                    .lock()
-                    .unwrap()
-                    .remove(&reply_context.qid)
-                    .unwrap();
-                barrier.wait();
+            ZenohBody::Data(Data {
+                reply_context,
+                payload,
+                ..
+            }) => {
+                // The replies of the queries already finalised are ignored
+                let qid = reply_context.unwrap().qid;
+                match self.pending.lock().unwrap().get_mut(&qid) {
+                    Some(query) => query.reply(payload.len()),
+                    None => log::warn!("Reply data for unknown query: {}", qid),
+                }
+            }
+            ZenohBody::Unit(Unit { reply_context, .. }) => {
+                // The final reply is signaled by a Unit message
+                let qid = reply_context.unwrap().qid;
+                let query = match self.pending.lock().unwrap().remove(&qid) {
+                    Some(query) => query,
+                    None => {
+                        log::warn!("Final reply for unknown query: {}", qid);
+                        return Ok(());
+                    }
+                };
+                let elapsed = query.instant.elapsed().as_micros();
+                self.breakdown
+                    .add(query.replies.iter().map(|(_, time)| *time), elapsed);
+                query.barrier.wait();
             }
             _ => panic!("Invalid message"),
         }
@@ -100,6 +122,10 @@ struct Opt {
     scenario: String,
     #[structopt(short = "p", long = "payload")]
     payload: usize,
+    #[structopt(long = "predicate-size", default_value = "0")]
+    predicate_size: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -113,6 +139,7 @@ async fn main() {
     let whatami = match opt.mode.as_str() {
         "peer" => whatami::PEER,
         "client" => whatami::CLIENT,
+        "router" => whatami::ROUTER,
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
 
@@ -121,39 +148,51 @@ async fn main() {
     rand::thread_rng().fill_bytes(&mut pid);
     let pid = PeerId::new(1, pid);
 
-    let rtt = Arc::new(AtomicUsize::new(0));
-    let counter = Arc::new(AtomicUsize::new(0));
+    let breakdown = Arc::new(Breakdown::default());
     let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+    let predicate = make_predicate(opt.predicate_size);
+    let predicate_size = predicate.len();
+    let opt_config = opt.tls.manager_config().await;
     let config = SessionManagerConfig {
         version: 0,
         whatami,
         id: pid,
-        handler: Arc::new(MySH::new(pending.clone())),
+        handler: Arc::new(MySH::new(pending.clone(), breakdown.clone())),
     };
-    let manager = SessionManager::new(config, None);
+    let manager = SessionManager::new(config, opt_config);
 
     // Connect to publisher
     let session = manager.open_session(&opt.locator).await.unwrap();
 
-    let c_rtt = rtt.clone();
-    let c_counter = counter.clone();
     task::spawn(async move {
         loop {
             let now = Instant::now();
             task::sleep(Duration::from_secs(1)).await;
             let elapsed = now.elapsed().as_micros() as f64;
 
-            let r = c_rtt.swap(0, Ordering::Relaxed);
-            let c = c_counter.swap(0, Ordering::Relaxed);
+            let sums = breakdown.take();
+            let c = sums.queries;
             if c > 0 {
                 let interval = 1_000_000.0 / elapsed;
                 println!(
-                    "session,{},query.throughput,{},{},{},{}",
+                    "session,{},query.throughput,{},{},{},{},{}",
                     opt.scenario,
                     opt.name,
                     opt.payload,
                     (c as f64 / interval).floor() as usize,
-                    (r as f64 / c as f64).floor() as usize,
+                    (sums.elapsed as f64 / c as f64).floor() as usize,
+                    predicate_size,
+                );
+                println!(
+                    "{}",
+                    latency::breakdown(
+                        "session",
+                        &opt.scenario,
+                        &opt.name,
+                        opt.payload,
+                        predicate_size,
+                        &sums
+                    )
                 );
             }
         }
@@ -163,7 +202,6 @@ async fn main() {
     loop {
         // Create and send the message
         let key = ResKey::RName("/test/query".to_string());
-        let predicate = "".to_string();
         let qid = count;
         let target = Some(QueryTarget::default());
         let consolidation = QueryConsolidation::default();
@@ -172,7 +210,7 @@ async fn main() {
 
         let message = ZenohMessage::make_query(
             key,
-            predicate,
+            predicate.clone(),
             qid,
             target,
             consolidation,
@@ -182,13 +220,11 @@ async fn main() {
 
         // Insert the pending query
         let barrier = Arc::new(Barrier::new(2));
-        pending.lock().unwrap().insert(count, barrier.clone());
-        let now = Instant::now();
+        let query = PendingQuery::new(barrier.clone());
+        pending.lock().unwrap().insert(count, query);
         session.handle_message(message).unwrap();
-        // Wait for the reply to arrive
+        // Wait for the final reply to arrive
         barrier.wait();
-        rtt.fetch_add(now.elapsed().as_micros() as usize, Ordering::Relaxed);
-        counter.fetch_add(1, Ordering::Relaxed);
 
         count += 1;
     }
@@ -15,6 +15,7 @@ use async_std::stream::StreamExt;
 use std::convert::TryFrom;
 use structopt::StructOpt;
 use zenoh::*;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "z_pong")]
@@ -25,6 +26,8 @@ struct Opt {
     mode: String,
     #[structopt(short = "p", long = "payload")]
     payload: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -38,9 +41,11 @@ async fn main() {
     let mut config = Properties::default();
     config.insert("mode".to_string(), opt.mode.clone());
 
+    opt.tls.configure(&mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     match opt.mode.as_str() {
-        "peer" => config.insert("listener".to_string(), opt.locator),
+        "peer" | "router" => config.insert("listener".to_string(), opt.locator),
         "client" => config.insert("peer".to_string(), opt.locator),
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
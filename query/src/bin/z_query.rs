@@ -16,6 +16,10 @@ use std::convert::TryInto;
 use std::time::Instant;
 use structopt::StructOpt;
 use zenoh::*;
+use zenoh_perf_common::router;
+use zenoh_perf_query::latency;
+use zenoh_perf_query::predicate::make_selector_predicate;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "z_query")]
@@ -28,6 +32,14 @@ struct Opt {
     name: String,
     #[structopt(short = "s", long = "scenario")]
     scenario: String,
+    #[structopt(long = "predicate", default_value = "")]
+    predicate: String,
+    // The size of the generated `?(x=0...)` predicate, 0 for none or at least
+    // 5 bytes, ignored when a predicate is given
+    #[structopt(long = "predicate-size", default_value = "0")]
+    predicate_size: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -41,19 +53,24 @@ async fn main() {
     let mut config = Properties::default();
     config.insert("mode".to_string(), opt.mode.clone());
 
+    opt.tls.configure(&mut config);
+
+    router::configure(&opt.mode, &mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     config.insert("peer".to_string(), opt.locator);
 
     let zenoh = Zenoh::new(config.into()).await.unwrap();
     let workspace = zenoh.workspace(None).await.unwrap();
 
+    let predicate = make_selector_predicate(&opt.predicate, opt.predicate_size);
     let mut count: u64 = 0;
     loop {
-        let selector = "/test/query".to_string();
+        let selector = format!("/test/query{}", predicate);
         let now = Instant::now();
         let mut data_stream = workspace.get(&selector.try_into().unwrap()).await.unwrap();
 
-        let mut payload: usize = 0;
+        let mut times: Vec<(usize, u128)> = vec![];
         while let Some(data) = data_stream.next().await {
             let len = match data.value {
                 Value::Raw(_, payload) => payload.len(),
@@ -74,17 +91,21 @@ async fn main() {
                 Value::Integer(_) => std::mem::size_of::<i64>(),
                 Value::Float(_) => std::mem::size_of::<f64>(),
             };
-            payload += len;
+            times.push((len, now.elapsed().as_micros()));
         }
+        // The stream is closed upon reception of the final reply
+        let elapsed = now.elapsed().as_micros();
 
-        println!(
-            "zenoh,{},query.latency,{},{},{},{}",
-            opt.scenario,
-            opt.name,
-            payload,
+        latency::print(
+            "zenoh",
+            &opt.scenario,
+            &opt.name,
+            predicate.len(),
             count,
-            now.elapsed().as_micros()
+            &times,
+            elapsed,
         );
+
         count += 1;
     }
 }
@@ -15,10 +15,13 @@ use async_std::stream::StreamExt;
 use async_std::sync::Arc;
 use async_std::task;
 use std::convert::TryInto;
-use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 use zenoh::*;
+use zenoh_perf_common::router;
+use zenoh_perf_query::latency::{self, Breakdown};
+use zenoh_perf_query::predicate::make_selector_predicate;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "z_query")]
@@ -33,6 +36,14 @@ struct Opt {
     scenario: String,
     #[structopt(short = "p", long = "payload")]
     payload: usize,
+    #[structopt(long = "predicate", default_value = "")]
+    predicate: String,
+    // The size of the generated `?(x=0...)` predicate, 0 for none or at least
+    // 5 bytes, ignored when a predicate is given
+    #[structopt(long = "predicate-size", default_value = "0")]
+    predicate_size: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -46,46 +57,64 @@ async fn main() {
     let mut config = Properties::default();
     config.insert("mode".to_string(), opt.mode.clone());
 
+    opt.tls.configure(&mut config);
+
+    router::configure(&opt.mode, &mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     config.insert("peer".to_string(), opt.locator.clone());
 
     let zenoh = Zenoh::new(config.into()).await.unwrap();
     let workspace = zenoh.workspace(None).await.unwrap();
 
-    let rtt = Arc::new(AtomicUsize::new(0));
-    let counter = Arc::new(AtomicUsize::new(0));
+    let breakdown = Arc::new(Breakdown::default());
 
-    let c_rtt = rtt.clone();
-    let c_counter = counter.clone();
+    let predicate = make_selector_predicate(&opt.predicate, opt.predicate_size);
+    let predicate_size = predicate.len();
+
+    let c_breakdown = breakdown.clone();
     task::spawn(async move {
         loop {
             let now = Instant::now();
             task::sleep(Duration::from_secs(1)).await;
             let elapsed = now.elapsed().as_micros() as f64;
 
-            let r = c_rtt.swap(0, Ordering::Relaxed);
-            let c = c_counter.swap(0, Ordering::Relaxed);
+            let sums = c_breakdown.take();
+            let c = sums.queries;
             if c > 0 {
                 let interval = 1_000_000.0 / elapsed;
                 println!(
-                    "zenoh,{},query.throughput,{},{},{},{}",
+                    "zenoh,{},query.throughput,{},{},{},{},{}",
                     opt.scenario,
                     opt.name,
                     opt.payload,
                     (c as f64 / interval).floor() as usize,
-                    (r as f64 / c as f64).floor() as usize,
+                    (sums.elapsed as f64 / c as f64).floor() as usize,
+                    predicate_size,
+                );
+                println!(
+                    "{}",
+                    latency::breakdown(
+                        "zenoh",
+                        &opt.scenario,
+                        &opt.name,
+                        opt.payload,
+                        predicate_size,
+                        &sums
+                    )
                 );
             }
         }
     });
 
     loop {
-        let selector = "/test/query".to_string();
+        let selector = format!("/test/query{}", predicate);
         let now = Instant::now();
         let mut data_stream = workspace.get(&selector.try_into().unwrap()).await.unwrap();
-        while data_stream.next().await.is_some() {}
-
-        rtt.fetch_add(now.elapsed().as_micros() as usize, Ordering::Relaxed);
-        counter.fetch_add(1, Ordering::Relaxed);
+        let mut times = vec![];
+        while data_stream.next().await.is_some() {
+            times.push(now.elapsed().as_micros());
+        }
+        breakdown.add(times, now.elapsed().as_micros());
     }
 }
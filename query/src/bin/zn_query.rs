@@ -17,6 +17,10 @@ use structopt::StructOpt;
 use zenoh::net::ResKey;
 use zenoh::net::*;
 use zenoh::Properties;
+use zenoh_perf_common::router;
+use zenoh_perf_query::latency;
+use zenoh_perf_query::predicate::make_predicate;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "zn_query")]
@@ -29,6 +33,10 @@ struct Opt {
     name: String,
     #[structopt(short = "s", long = "scenario")]
     scenario: String,
+    #[structopt(long = "predicate-size", default_value = "0")]
+    predicate_size: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -42,35 +50,43 @@ async fn main() {
     let mut config = Properties::default();
     config.insert("mode".to_string(), opt.mode.clone());
 
+    opt.tls.configure(&mut config);
+
+    router::configure(&opt.mode, &mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     config.insert("peer".to_string(), opt.locator);
 
     let session = open(config.into()).await.unwrap();
 
+    let predicate = make_predicate(opt.predicate_size);
     let mut count: u64 = 0;
     loop {
         let reskey = ResKey::RName("/test/query".to_string());
-        let predicate = "";
         let target = QueryTarget::default();
         let consolidation = QueryConsolidation::default();
 
         let now = Instant::now();
         let mut replies = session
-            .query(&reskey, predicate, target, consolidation)
+            .query(&reskey, &predicate, target, consolidation)
             .await
             .unwrap();
 
-        let mut payload: usize = 0;
+        let mut times: Vec<(usize, u128)> = vec![];
         while let Some(reply) = replies.next().await {
-            payload += reply.data.payload.len();
+            times.push((reply.data.payload.len(), now.elapsed().as_micros()));
         }
-        println!(
-            "zenoh-net,{},query.latency,{},{},{},{}",
-            opt.scenario,
-            opt.name,
-            payload,
+        // The stream is closed upon reception of the final reply
+        let elapsed = now.elapsed().as_micros();
+
+        latency::print(
+            "zenoh-net",
+            &opt.scenario,
+            &opt.name,
+            predicate.len(),
             count,
-            now.elapsed().as_micros()
+            &times,
+            elapsed,
         );
 
         count += 1;
@@ -14,12 +14,15 @@
 use async_std::stream::StreamExt;
 use async_std::sync::Arc;
 use async_std::task;
-use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 use zenoh::net::ResKey;
 use zenoh::net::*;
 use zenoh::Properties;
+use zenoh_perf_common::router;
+use zenoh_perf_query::latency::{self, Breakdown};
+use zenoh_perf_query::predicate::make_predicate;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "zn_query")]
@@ -34,6 +37,10 @@ struct Opt {
     scenario: String,
     #[structopt(short = "p", long = "payload")]
     payload: usize,
+    #[structopt(long = "predicate-size", default_value = "0")]
+    predicate_size: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -47,33 +54,50 @@ async fn main() {
     let mut config = Properties::default();
     config.insert("mode".to_string(), opt.mode.clone());
 
+    opt.tls.configure(&mut config);
+
+    router::configure(&opt.mode, &mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     config.insert("peer".to_string(), opt.locator.clone());
 
     let session = open(config.into()).await.unwrap();
 
-    let rtt = Arc::new(AtomicUsize::new(0));
-    let counter = Arc::new(AtomicUsize::new(0));
+    let breakdown = Arc::new(Breakdown::default());
 
-    let c_rtt = rtt.clone();
-    let c_counter = counter.clone();
+    let predicate = make_predicate(opt.predicate_size);
+    let predicate_size = predicate.len();
+
+    let c_breakdown = breakdown.clone();
     task::spawn(async move {
         loop {
             let now = Instant::now();
             task::sleep(Duration::from_secs(1)).await;
             let elapsed = now.elapsed().as_micros() as f64;
 
-            let r = c_rtt.swap(0, Ordering::Relaxed);
-            let c = c_counter.swap(0, Ordering::Relaxed);
+            let sums = c_breakdown.take();
+            let c = sums.queries;
             if c > 0 {
                 let interval = 1_000_000.0 / elapsed;
                 println!(
-                    "zenoh-net,{},query.throughput,{},{},{},{}",
+                    "zenoh-net,{},query.throughput,{},{},{},{},{}",
                     opt.scenario,
                     opt.name,
                     opt.payload,
                     (c as f64 / interval).floor() as usize,
-                    (r as f64 / c as f64).floor() as usize
+                    (sums.elapsed as f64 / c as f64).floor() as usize,
+                    predicate_size,
+                );
+                println!(
+                    "{}",
+                    latency::breakdown(
+                        "zenoh-net",
+                        &opt.scenario,
+                        &opt.name,
+                        opt.payload,
+                        predicate_size,
+                        &sums
+                    )
                 );
             }
         }
@@ -81,18 +105,18 @@ async fn main() {
 
     loop {
         let reskey = ResKey::RName("/test/query".to_string());
-        let predicate = "";
         let target = QueryTarget::default();
         let consolidation = QueryConsolidation::default();
 
         let now = Instant::now();
         let mut replies = session
-            .query(&reskey, predicate, target, consolidation)
+            .query(&reskey, &predicate, target, consolidation)
             .await
             .unwrap();
-        while replies.next().await.is_some() {}
-
-        rtt.fetch_add(now.elapsed().as_micros() as usize, Ordering::Relaxed);
-        counter.fetch_add(1, Ordering::Relaxed);
+        let mut times = vec![];
+        while replies.next().await.is_some() {
+            times.push(now.elapsed().as_micros());
+        }
+        breakdown.add(times, now.elapsed().as_micros());
     }
 }
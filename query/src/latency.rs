@@ -0,0 +1,251 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Barrier, Mutex};
+use std::time::Instant;
+
+// An in-flight query: when it was sent, the (payload, elapsed) of each reply
+// received so far and the barrier to release once the final reply arrives
+pub struct PendingQuery {
+    pub instant: Instant,
+    pub replies: Vec<(usize, u128)>,
+    pub barrier: Arc<Barrier>,
+}
+
+impl PendingQuery {
+    pub fn new(barrier: Arc<Barrier>) -> PendingQuery {
+        PendingQuery {
+            instant: Instant::now(),
+            replies: vec![],
+            barrier,
+        }
+    }
+
+    pub fn reply(&mut self, payload: usize) {
+        self.replies
+            .push((payload, self.instant.elapsed().as_micros()));
+    }
+}
+
+pub type Pending = Arc<Mutex<HashMap<u64, PendingQuery>>>;
+
+// The records of a query, whatever the layer:
+// - query.latency.first: payload and time of the first reply, or of the final
+//   one with a 0 payload when there is no reply
+// - query.latency.reply: payload, index and time of each reply
+// - query.latency.final: summed payload, number of replies and time of the
+//   final reply
+#[allow(clippy::too_many_arguments)]
+pub fn records(
+    layer: &str,
+    scenario: &str,
+    name: &str,
+    predicate: usize,
+    qid: u64,
+    replies: &[(usize, u128)],
+    elapsed: u128,
+) -> Vec<String> {
+    let mut records = Vec::with_capacity(replies.len() + 2);
+    let (payload, first) = replies.first().copied().unwrap_or((0, elapsed));
+    records.push(format!(
+        "{},{},query.latency.first,{},{},{},{},{}",
+        layer, scenario, name, payload, predicate, qid, first
+    ));
+    for (i, (payload, time)) in replies.iter().enumerate() {
+        records.push(format!(
+            "{},{},query.latency.reply,{},{},{},{},{},{}",
+            layer, scenario, name, payload, predicate, qid, i, time
+        ));
+    }
+    let payload: usize = replies.iter().map(|(p, _)| p).sum();
+    records.push(format!(
+        "{},{},query.latency.final,{},{},{},{},{},{}",
+        layer,
+        scenario,
+        name,
+        payload,
+        predicate,
+        qid,
+        replies.len(),
+        elapsed
+    ));
+    records
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn print(
+    layer: &str,
+    scenario: &str,
+    name: &str,
+    predicate: usize,
+    qid: u64,
+    replies: &[(usize, u128)],
+    elapsed: u128,
+) {
+    for record in records(layer, scenario, name, predicate, qid, replies, elapsed) {
+        println!("{}", record);
+    }
+}
+
+// The timings of the queries of a throughput run, summed until taken
+#[derive(Default)]
+pub struct Breakdown {
+    queries: AtomicUsize,
+    first: AtomicUsize,
+    replies: AtomicUsize,
+    gaps: AtomicUsize,
+    gaps_time: AtomicUsize,
+    elapsed: AtomicUsize,
+}
+
+// The sums taken from a breakdown: the number of queries, the times of their
+// first reply (of the final one when there is no reply), the number of
+// replies, the number and time of the gaps in between consecutive replies and
+// the times of the final replies
+#[derive(Debug, Default, PartialEq)]
+pub struct Sums {
+    pub queries: usize,
+    pub first: usize,
+    pub replies: usize,
+    pub gaps: usize,
+    pub gaps_time: usize,
+    pub elapsed: usize,
+}
+
+impl Breakdown {
+    // Account a query from the times of its replies and of its final reply
+    pub fn add<I: IntoIterator<Item = u128>>(&self, replies: I, elapsed: u128) {
+        let mut first = None;
+        let mut last = None;
+        let mut count = 0;
+        let mut gaps_time = 0;
+        for time in replies {
+            if let Some(last) = last {
+                gaps_time += time - last;
+            }
+            first.get_or_insert(time);
+            last = Some(time);
+            count += 1;
+        }
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        self.first
+            .fetch_add(first.unwrap_or(elapsed) as usize, Ordering::Relaxed);
+        self.replies.fetch_add(count, Ordering::Relaxed);
+        if count > 1 {
+            self.gaps.fetch_add(count - 1, Ordering::Relaxed);
+            self.gaps_time
+                .fetch_add(gaps_time as usize, Ordering::Relaxed);
+        }
+        self.elapsed.fetch_add(elapsed as usize, Ordering::Relaxed);
+    }
+
+    pub fn take(&self) -> Sums {
+        Sums {
+            queries: self.queries.swap(0, Ordering::Relaxed),
+            first: self.first.swap(0, Ordering::Relaxed),
+            replies: self.replies.swap(0, Ordering::Relaxed),
+            gaps: self.gaps.swap(0, Ordering::Relaxed),
+            gaps_time: self.gaps_time.swap(0, Ordering::Relaxed),
+            elapsed: self.elapsed.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+fn mean(sum: usize, count: usize) -> usize {
+    if count == 0 {
+        return 0;
+    }
+    (sum as f64 / count as f64).floor() as usize
+}
+
+// The query.breakdown record of the sums: the mean times of the first and of
+// the final reply, the number of replies and the mean time in between
+// consecutive replies
+pub fn breakdown(
+    layer: &str,
+    scenario: &str,
+    name: &str,
+    payload: usize,
+    predicate: usize,
+    sums: &Sums,
+) -> String {
+    format!(
+        "{},{},query.breakdown,{},{},{},{},{},{},{}",
+        layer,
+        scenario,
+        name,
+        payload,
+        predicate,
+        mean(sums.first, sums.queries),
+        mean(sums.elapsed, sums.queries),
+        sums.replies,
+        mean(sums.gaps_time, sums.gaps)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_of_several_replies() {
+        let records = records("zenoh", "s", "n", 3, 7, &[(10, 100), (20, 150)], 200);
+        assert_eq!(
+            records,
+            vec![
+                "zenoh,s,query.latency.first,n,10,3,7,100",
+                "zenoh,s,query.latency.reply,n,10,3,7,0,100",
+                "zenoh,s,query.latency.reply,n,20,3,7,1,150",
+                "zenoh,s,query.latency.final,n,30,3,7,2,200",
+            ]
+        );
+    }
+
+    #[test]
+    fn records_without_replies() {
+        let records = records("router", "s", "n", 0, 1, &[], 50);
+        assert_eq!(
+            records,
+            vec![
+                "router,s,query.latency.first,n,0,0,1,50",
+                "router,s,query.latency.final,n,0,0,1,0,50",
+            ]
+        );
+    }
+
+    #[test]
+    fn breakdown_of_several_queries() {
+        let breakdown = Breakdown::default();
+        breakdown.add(vec![10, 30, 60], 100);
+        breakdown.add(vec![], 50);
+        let sums = breakdown.take();
+        assert_eq!(
+            sums,
+            Sums {
+                queries: 2,
+                first: 60,
+                replies: 3,
+                gaps: 2,
+                gaps_time: 50,
+                elapsed: 150,
+            }
+        );
+        assert_eq!(
+            self::breakdown("router", "s", "n", 8, 0, &sums),
+            "router,s,query.breakdown,n,8,0,30,75,3,25"
+        );
+        assert_eq!(breakdown.take(), Sums::default());
+    }
+}
@@ -0,0 +1,71 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+
+// The smallest selector predicate, `?(x=)`
+pub const MIN_SELECTOR_SIZE: usize = 5;
+
+// Build a `key=value` predicate of exactly `size` bytes
+pub fn make_predicate(size: usize) -> String {
+    match size {
+        0 => String::new(),
+        1 => "x".to_string(),
+        _ => format!("x={}", "0".repeat(size - 2)),
+    }
+}
+
+// Build a `?(key=value)` selector predicate of exactly `size` bytes, or use
+// the given template when provided (e.g. `?(starttime=now()-1h)`). A size
+// between 1 and MIN_SELECTOR_SIZE - 1 cannot be built and is rejected.
+pub fn make_selector_predicate(template: &str, size: usize) -> String {
+    if !template.is_empty() || size == 0 {
+        return template.to_string();
+    }
+    if size < MIN_SELECTOR_SIZE {
+        panic!("Unsupported predicate size: {}", size);
+    }
+    format!("?(x={})", "0".repeat(size - MIN_SELECTOR_SIZE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn predicate_has_the_requested_size() {
+        for size in 0..64 {
+            assert_eq!(make_predicate(size).len(), size);
+        }
+    }
+
+    #[test]
+    fn selector_predicate_has_the_requested_size() {
+        assert_eq!(make_selector_predicate("", 0), "");
+        assert_eq!(make_selector_predicate("", 5), "?(x=)");
+        for size in MIN_SELECTOR_SIZE..64 {
+            assert_eq!(make_selector_predicate("", size).len(), size);
+        }
+    }
+
+    #[test]
+    fn selector_predicate_uses_the_template() {
+        let template = "?(starttime=now()-1h)";
+        assert_eq!(make_selector_predicate(template, 3), template);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported predicate size: 4")]
+    fn selector_predicate_rejects_small_sizes() {
+        make_selector_predicate("", 4);
+    }
+}
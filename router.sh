@@ -0,0 +1,35 @@
+# The functions the scenarios source to spawn the zn_router of the throughput
+# crate when run with ROUTER=1, from the directory of their crate:
+#   . ../router.sh
+#   start_router tcp/127.0.0.1:7447
+#   ...
+#   print_router_stats scenario,payload
+#   stop_router
+ROUTER_STATS=${ROUTER_STATS:-127.0.0.1:8000}
+ROUTER_TIMEOUT=${ROUTER_TIMEOUT:-10}
+
+# Spawn the router listening on the locator and wait for its stats endpoint to
+# answer, which it only does once the locator is bound
+start_router() {
+    ../target/release/zn_router -l $1 --stats $ROUTER_STATS &
+    ROUTER_PID=$!
+    local deadline=$((SECONDS + ROUTER_TIMEOUT))
+    until curl -s -o /dev/null http://$ROUTER_STATS/; do
+        if ! kill -0 $ROUTER_PID 2>/dev/null || [ $SECONDS -ge $deadline ]; then
+            echo "zn_router did not answer on $ROUTER_STATS" >&2
+            kill $ROUTER_PID 2>/dev/null
+            exit 1
+        fi
+        sleep 0.1
+    done
+}
+
+# Print the stats of the router, each record prefixed with the given fields
+print_router_stats() {
+    curl -s http://$ROUTER_STATS/ | sed "s/^/zn_router,$1,/"
+}
+
+stop_router() {
+    kill $ROUTER_PID
+    wait $ROUTER_PID 2>/dev/null
+}
@@ -0,0 +1,62 @@
+#!/usr/bin/env bash
+
+# Sweep the payload from 1 KB to 64 MB over TCP, UDP and Unix sockets to find
+# out where the fragmentation overhead kicks in, the resident memory of the
+# whole receiving process is printed every second along with the results.
+# Each payload is then published to the session-layer sink for
+# SINK_DURATION seconds, which reports the fragments per message and the bytes
+# reassembled per message. The unixsock-stream variant requires the binaries
+# to be built with the transport_unixsock-stream feature.
+#
+# With ROUTER=1 the zn_router of the throughput crate is spawned on the locator
+# and both sides connect to it in client mode, its stats are printed at the
+# end of each run.
+DURATION=${DURATION:-10}
+SINK_DURATION=${SINK_DURATION:-5}
+UNIXSOCK_PATH=${UNIXSOCK_PATH:-/tmp/zenoh-perf.sock}
+PROTOS=${PROTOS:-tcp udp unixsock-stream}
+ROUTER=${ROUTER:-0}
+
+. ../router.sh
+
+for proto in $PROTOS; do
+    case $proto in
+        udp) locator=udp/127.0.0.1:7447; sink="s_sink_udp -l 127.0.0.1:7447" ;;
+        unixsock-stream) locator=unixsock-stream/$UNIXSOCK_PATH; sink="s_sink_tcp -t unixsock-stream -l $UNIXSOCK_PATH" ;;
+        *) locator=tcp/127.0.0.1:7447; sink="s_sink_tcp -l 127.0.0.1:7447" ;;
+    esac
+    mode=peer
+    if [ "$ROUTER" = 1 ]; then
+        mode=client
+    fi
+    for exp in $(seq 10 26); do
+        payload=$((1 << exp))
+        if [ "$ROUTER" = 1 ]; then
+            start_router $locator
+        fi
+        ../target/release/zn_sub_thr -l $locator -m $mode -p $payload -n zn_sub_thr -s fragmentation.$proto --memory &
+        sub=$!
+        sleep 1
+        ../target/release/zn_pub_thr -l $locator -m $mode -p $payload &
+        pub=$!
+        sleep $DURATION
+        if [ "$ROUTER" = 1 ]; then
+            print_router_stats fragmentation.$proto,$payload
+        fi
+        kill $pub $sub
+        wait $pub $sub 2>/dev/null
+        if [ "$ROUTER" = 1 ]; then
+            stop_router
+        fi
+
+        # The sink decodes the frames the publisher sends it directly
+        ../target/release/$sink -d > >(sed "s/^/s_sink,fragmentation.$proto,$payload,/") &
+        sink_pid=$!
+        sleep 1
+        ../target/release/zn_pub_thr -l $locator -m peer -p $payload &
+        pub=$!
+        sleep $SINK_DURATION
+        kill $pub $sink_pid
+        wait $pub $sink_pid 2>/dev/null
+    done
+done
@@ -0,0 +1,272 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::sync::Arc;
+use async_std::task;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use structopt::StructOpt;
+use zenoh::net::protocol::core::{
+    Channel, PeerId, Priority, QueryConsolidation, QueryTarget, Reliability, ResKey, SubInfo,
+    SubMode, Target, ZInt,
+};
+use zenoh::net::protocol::io::ZBuf;
+use zenoh::net::protocol::proto::{DataInfo, RoutingContext};
+use zenoh::net::protocol::session::Primitives;
+use zenoh::net::queryable::ALL_KINDS;
+use zenoh::net::routing::face::Face;
+use zenoh::net::runtime::Runtime;
+use zenoh_util::properties::config::{
+    ConfigProperties, ZN_ADD_TIMESTAMP_KEY, ZN_MODE_KEY, ZN_MULTICAST_SCOUTING_KEY,
+};
+
+// The faces of the benchmark, which count the messages the router delivers to
+// them. The queries are answered right away for the router to release them,
+// from a task since the router is locked while it delivers them.
+struct LocalPrimitives {
+    count: Arc<AtomicUsize>,
+    tx: Mutex<Option<Arc<Face>>>,
+}
+
+impl LocalPrimitives {
+    fn new(count: Arc<AtomicUsize>) -> LocalPrimitives {
+        LocalPrimitives {
+            count,
+            tx: Mutex::new(None),
+        }
+    }
+
+    fn set_tx(&self, tx: Arc<Face>) {
+        let mut guard = self.tx.lock().unwrap();
+        *guard = Some(tx);
+    }
+}
+
+impl Primitives for LocalPrimitives {
+    fn decl_resource(&self, _rid: ZInt, _reskey: &ResKey) {}
+
+    fn forget_resource(&self, _rid: ZInt) {}
+
+    fn decl_publisher(&self, _reskey: &ResKey, _routing_context: Option<RoutingContext>) {}
+
+    fn forget_publisher(&self, _reskey: &ResKey, _routing_context: Option<RoutingContext>) {}
+
+    fn decl_subscriber(
+        &self,
+        _reskey: &ResKey,
+        _sub_info: &SubInfo,
+        _routing_context: Option<RoutingContext>,
+    ) {
+    }
+
+    fn forget_subscriber(&self, _reskey: &ResKey, _routing_context: Option<RoutingContext>) {}
+
+    fn decl_queryable(
+        &self,
+        _reskey: &ResKey,
+        _kind: ZInt,
+        _routing_context: Option<RoutingContext>,
+    ) {
+    }
+
+    fn forget_queryable(&self, _reskey: &ResKey, _routing_context: Option<RoutingContext>) {}
+
+    fn send_data(
+        &self,
+        _reskey: &ResKey,
+        _payload: ZBuf,
+        _channel: Channel,
+        _data_info: Option<DataInfo>,
+        _routing_context: Option<RoutingContext>,
+    ) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn send_query(
+        &self,
+        _reskey: &ResKey,
+        _predicate: &str,
+        qid: ZInt,
+        _target: QueryTarget,
+        _consolidation: QueryConsolidation,
+        _routing_context: Option<RoutingContext>,
+    ) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        if let Some(tx) = self.tx.lock().unwrap().as_ref().cloned() {
+            // @TODO: once the router is re-entrant remove the task spawn
+            task::spawn(async move {
+                tx.send_reply_final(qid);
+            });
+        }
+    }
+
+    fn send_reply_data(
+        &self,
+        _qid: ZInt,
+        _source_kind: ZInt,
+        _replier_id: PeerId,
+        _reskey: ResKey,
+        _info: Option<DataInfo>,
+        _payload: ZBuf,
+    ) {
+    }
+
+    fn send_reply_final(&self, _qid: ZInt) {}
+
+    fn send_pull(
+        &self,
+        _is_final: bool,
+        _reskey: &ResKey,
+        _pull_id: ZInt,
+        _max_samples: &Option<ZInt>,
+    ) {
+    }
+
+    fn send_close(&self) {}
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "r_local_thr")]
+struct Opt {
+    // peer or router, which select the routing tables
+    #[structopt(short = "m", long = "mode", default_value = "peer")]
+    mode: String,
+    #[structopt(short = "p", long = "payload")]
+    payload: usize,
+    #[structopt(short = "n", long = "name")]
+    name: String,
+    #[structopt(short = "s", long = "scenario")]
+    scenario: String,
+    // The number of faces the messages of the source face are routed to
+    #[structopt(short = "f", long = "faces", default_value = "1")]
+    faces: usize,
+    // data: the faces subscribe and the source face sends data, or query: the
+    // faces are queryables and the source face sends queries
+    #[structopt(short = "k", long = "kind", default_value = "data")]
+    kind: String,
+}
+
+#[async_std::main]
+async fn main() {
+    // Enable logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    match opt.mode.as_str() {
+        "peer" | "router" => {}
+        _ => panic!("Unsupported mode: {}", opt.mode),
+    }
+    if opt.faces == 0 {
+        panic!("Unsupported faces: {}", opt.faces);
+    }
+
+    // No listener nor peer, the messages never leave the process
+    let mut config = ConfigProperties::default();
+    config.insert(ZN_MODE_KEY, opt.mode.clone());
+    config.insert(ZN_ADD_TIMESTAMP_KEY, "false".to_string());
+    config.insert(ZN_MULTICAST_SCOUTING_KEY, "false".to_string());
+
+    let runtime = Runtime::new(0u8, config, None).await.unwrap();
+
+    let rid = ResKey::RName("/test/thr".to_string());
+    let delivered = Arc::new(AtomicUsize::new(0));
+    // The faces are kept open for the router to keep routing to them
+    let mut faces = Vec::with_capacity(opt.faces);
+    for _ in 0..opt.faces {
+        let rx_primitives = Arc::new(LocalPrimitives::new(delivered.clone()));
+        let tx_primitives = runtime.router.new_primitives(rx_primitives.clone());
+        rx_primitives.set_tx(tx_primitives.clone());
+        match opt.kind.as_str() {
+            "data" => {
+                let sub_info = SubInfo {
+                    reliability: Reliability::Reliable,
+                    mode: SubMode::Push,
+                    period: None,
+                };
+                tx_primitives.decl_subscriber(&rid, &sub_info, None);
+            }
+            "query" => tx_primitives.decl_queryable(&rid, ALL_KINDS, None),
+            _ => panic!("Unsupported kind: {}", opt.kind),
+        }
+        faces.push(tx_primitives);
+    }
+
+    let rx_primitives = Arc::new(LocalPrimitives::new(Arc::new(AtomicUsize::new(0))));
+    let source = runtime.router.new_primitives(rx_primitives);
+    source.decl_resource(1, &rid);
+    let rid = ResKey::RId(1);
+    if opt.kind == "data" {
+        source.decl_publisher(&rid, None);
+    }
+
+    let channel = Channel {
+        priority: Priority::Data,
+        reliability: Reliability::Reliable,
+    };
+    // Query all the queryables, the default target only selects the best one
+    let target = QueryTarget {
+        kind: ALL_KINDS,
+        target: Target::All,
+    };
+    let payload = ZBuf::from(vec![0u8; opt.payload]);
+
+    // The messages are routed in the calls of the source face, the rate is
+    // checked every batch of messages not to measure the clock
+    const BATCH: usize = 1_024;
+    let mut qid: ZInt = 0;
+    let mut sent: usize = 0;
+    let mut now = Instant::now();
+    loop {
+        for _ in 0..BATCH {
+            match opt.kind.as_str() {
+                "data" => source.send_data(&rid, payload.clone(), channel, None, None),
+                _ => {
+                    source.send_query(
+                        &rid,
+                        "",
+                        qid,
+                        target.clone(),
+                        QueryConsolidation::default(),
+                        None,
+                    );
+                    qid += 1;
+                }
+            }
+        }
+        sent += BATCH;
+
+        let elapsed = now.elapsed();
+        if elapsed.as_secs() >= 1 {
+            let elapsed = elapsed.as_micros() as f64;
+            let d = delivered.swap(0, Ordering::Relaxed);
+            // The messages sent by the source face and the ones delivered to
+            // all the faces, per second
+            println!(
+                "router-local,{},routing.{}.{},{},{},{},{},{}",
+                opt.scenario,
+                opt.kind,
+                opt.mode,
+                opt.name,
+                opt.payload,
+                opt.faces,
+                (sent as f64 * 1_000_000.0 / elapsed).floor() as usize,
+                (d as f64 * 1_000_000.0 / elapsed).floor() as usize
+            );
+            sent = 0;
+            now = Instant::now();
+        }
+    }
+}
@@ -22,6 +22,10 @@ use zenoh::net::protocol::io::ZBuf;
 use zenoh::net::protocol::session::DummyPrimitives;
 use zenoh::net::protocol::session::Primitives;
 use zenoh::net::runtime::Runtime;
+use zenoh_perf_common::reliability::reliability;
+use zenoh_perf_common::router;
+use zenoh_perf_common::seq::{check_best_effort_payload, stamp};
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::properties::config::{
     ConfigProperties, ZN_ADD_TIMESTAMP_KEY, ZN_MODE_KEY, ZN_MULTICAST_SCOUTING_KEY, ZN_PEER_KEY,
 };
@@ -40,6 +44,10 @@ struct Opt {
     print: bool,
     #[structopt(short = "c", long = "conf", parse(from_os_str))]
     config: Option<PathBuf>,
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -50,6 +58,9 @@ async fn main() {
     // Parse the args
     let opt = Opt::from_args();
 
+    let reliability = reliability(&opt.reliability);
+    check_best_effort_payload(reliability, opt.payload);
+
     let mut config = match opt.config.as_ref() {
         Some(f) => {
             let config = async_std::fs::read_to_string(f).await.unwrap();
@@ -61,6 +72,10 @@ async fn main() {
     config.insert(ZN_MODE_KEY, opt.mode.clone());
     config.insert(ZN_ADD_TIMESTAMP_KEY, "false".to_string());
 
+    opt.tls.configure_runtime(&mut config);
+
+    router::configure_runtime(&opt.mode, &mut config);
+
     config.insert(ZN_MULTICAST_SCOUTING_KEY, "false".to_string());
     config.insert(ZN_PEER_KEY, opt.locator);
 
@@ -79,9 +94,10 @@ async fn main() {
 
     let channel = Channel {
         priority: Priority::Data,
-        reliability: Reliability::Reliable,
+        reliability,
     };
-    let payload = ZBuf::from(vec![0u8; opt.payload]);
+    let bytes = vec![0u8; opt.payload];
+    let payload = ZBuf::from(bytes.clone());
     if opt.print {
         let count = Arc::new(AtomicUsize::new(0));
         let c_count = count.clone();
@@ -95,13 +111,25 @@ async fn main() {
             }
         });
 
+        let mut seq: u64 = 0;
         loop {
-            primitives.send_data(&rid, payload.clone(), channel, None, None);
+            let payload = match reliability {
+                Reliability::Reliable => payload.clone(),
+                Reliability::BestEffort => stamp(&bytes, seq),
+            };
+            primitives.send_data(&rid, payload, channel, None, None);
             c_count.fetch_add(1, Ordering::Relaxed);
+            seq += 1;
         }
     } else {
+        let mut seq: u64 = 0;
         loop {
-            primitives.send_data(&rid, payload.clone(), channel, None, None);
+            let payload = match reliability {
+                Reliability::Reliable => payload.clone(),
+                Reliability::BestEffort => stamp(&bytes, seq),
+            };
+            primitives.send_data(&rid, payload, channel, None, None);
+            seq += 1;
         }
     }
 }
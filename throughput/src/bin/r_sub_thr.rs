@@ -14,7 +14,7 @@
 use async_std::sync::Arc;
 use async_std::task;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 use zenoh::net::protocol::core::{
@@ -24,6 +24,10 @@ use zenoh::net::protocol::io::ZBuf;
 use zenoh::net::protocol::proto::{DataInfo, RoutingContext};
 use zenoh::net::protocol::session::Primitives;
 use zenoh::net::runtime::Runtime;
+use zenoh_perf_common::reliability::reliability;
+use zenoh_perf_common::seq::account_payload;
+use zenoh_perf_common::sub::SubOpt;
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::properties::config::{
     ConfigProperties, ZN_LISTENER_KEY, ZN_MODE_KEY, ZN_MULTICAST_SCOUTING_KEY, ZN_PEER_KEY,
 };
@@ -31,11 +35,23 @@ use zenoh_util::properties::{IntKeyProperties, Properties};
 
 struct ThroughputPrimitives {
     count: Arc<AtomicUsize>,
+    best_effort: bool,
+    next: AtomicU64,
+    lost: Arc<AtomicUsize>,
 }
 
 impl ThroughputPrimitives {
-    pub fn new(count: Arc<AtomicUsize>) -> ThroughputPrimitives {
-        ThroughputPrimitives { count }
+    pub fn new(
+        count: Arc<AtomicUsize>,
+        best_effort: bool,
+        lost: Arc<AtomicUsize>,
+    ) -> ThroughputPrimitives {
+        ThroughputPrimitives {
+            count,
+            best_effort,
+            next: AtomicU64::new(0),
+            lost,
+        }
     }
 }
 
@@ -85,12 +101,15 @@ impl Primitives for ThroughputPrimitives {
     fn send_data(
         &self,
         _reskey: &ResKey,
-        _payload: ZBuf,
+        mut payload: ZBuf,
         _channel: Channel,
         _data_info: Option<DataInfo>,
         _routing_context: Option<RoutingContext>,
     ) {
         self.count.fetch_add(1, Ordering::Relaxed);
+        if self.best_effort {
+            account_payload(&self.next, &self.lost, &mut payload);
+        }
     }
 
     fn send_query(
@@ -151,6 +170,12 @@ struct Opt {
     scenario: String,
     #[structopt(short = "c", long = "conf", parse(from_os_str))]
     config: Option<PathBuf>,
+    #[structopt(flatten)]
+    sub: SubOpt,
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -171,6 +196,8 @@ async fn main() {
     };
     config.insert(ZN_MODE_KEY, opt.mode.clone());
 
+    opt.tls.configure_runtime(&mut config);
+
     config.insert(ZN_MULTICAST_SCOUTING_KEY, "false".to_string());
     match opt.mode.as_str() {
         "peer" | "router" => {
@@ -184,22 +211,48 @@ async fn main() {
         }
     }
 
+    let reliability = reliability(&opt.reliability);
+
     let count = Arc::new(AtomicUsize::new(0));
-    let my_primitives = Arc::new(ThroughputPrimitives::new(count.clone()));
+    let lost = Arc::new(AtomicUsize::new(0));
+    let my_primitives = Arc::new(ThroughputPrimitives::new(
+        count.clone(),
+        reliability == Reliability::BestEffort,
+        lost.clone(),
+    ));
 
     let runtime = Runtime::new(0u8, config, None).await.unwrap();
     let primitives = runtime.router.new_primitives(my_primitives);
 
     primitives.decl_resource(1, &"/test/thr".to_string().into());
 
+    let mode = opt.sub.mode();
+    let period = opt.sub.period();
+    let kind = opt.sub.kind("throughput");
+
     let rid = ResKey::RId(1);
     let sub_info = SubInfo {
-        reliability: Reliability::Reliable,
-        mode: SubMode::Push,
-        period: None,
+        reliability,
+        mode,
+        period,
     };
     primitives.decl_subscriber(&rid, &sub_info, None);
 
+    if sub_info.mode == SubMode::Pull {
+        // Pull the available samples at the given cadence
+        let sleep = opt.sub.pull_interval();
+        let max_samples = opt.sub.max_samples;
+        let c_primitives = primitives.clone();
+        task::spawn(async move {
+            let mut pull_id: ZInt = 0;
+            loop {
+                c_primitives.send_pull(true, &rid, pull_id, &max_samples);
+                task::sleep(sleep).await;
+                pull_id += 1;
+            }
+        });
+    }
+
     loop {
         let now = Instant::now();
         task::sleep(Duration::from_secs(1)).await;
@@ -209,12 +262,22 @@ async fn main() {
         if c > 0 {
             let interval = 1_000_000.0 / elapsed;
             println!(
-                "router,{},throughput,{},{},{}",
+                "router,{},{},{},{},{}",
                 opt.scenario,
+                kind,
                 opt.name,
                 opt.payload,
                 (c as f64 / interval).floor() as usize
             );
+            if reliability == Reliability::BestEffort {
+                println!(
+                    "router,{},loss,{},{},{}",
+                    opt.scenario,
+                    opt.name,
+                    opt.payload,
+                    lost.swap(0, Ordering::Relaxed)
+                );
+            }
         }
     }
 }
@@ -0,0 +1,129 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::io;
+use async_std::net::{SocketAddr, TcpStream, UdpSocket};
+#[cfg(all(unix, feature = "transport_unixsock-stream"))]
+use async_std::os::unix::net::UnixStream;
+use async_std::prelude::*;
+use async_std::sync::Arc;
+use async_std::task;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use structopt::StructOpt;
+
+// The largest UDP payload over IPv4
+const MAX_DATAGRAM: usize = 65_507;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "raw_pub_thr")]
+struct Opt {
+    // tcp, udp, or unixsock-stream when built with the
+    // transport_unixsock-stream feature, the locator being the socket path.
+    // No short option, -t prints the rate like zn_pub_thr
+    #[structopt(long = "transport", default_value = "tcp")]
+    transport: String,
+    #[structopt(short = "l", long = "locator")]
+    locator: String,
+    #[structopt(short = "p", long = "payload")]
+    payload: usize,
+    #[structopt(short = "t", long = "print")]
+    print: bool,
+}
+
+// Mirror the framing of the zenoh batches, prefixed by their length on 16 bits
+async fn pub_stream<S: io::Write + Unpin>(mut stream: S, bytes: &[u8], count: &AtomicUsize) {
+    let mut message = (bytes.len() as u16).to_le_bytes().to_vec();
+    message.extend_from_slice(bytes);
+    let mut seq: u64 = 0;
+    loop {
+        message[2..10].copy_from_slice(&seq.to_le_bytes());
+        stream.write_all(&message).await.unwrap();
+        count.fetch_add(1, Ordering::Relaxed);
+        seq += 1;
+    }
+}
+
+async fn pub_udp(locator: &str, mut message: Vec<u8>, count: &AtomicUsize) {
+    let locator: SocketAddr = locator.parse().unwrap();
+    let local: SocketAddr = if locator.is_ipv4() {
+        "0.0.0.0:0".parse().unwrap()
+    } else {
+        "[::]:0".parse().unwrap()
+    };
+    let socket = UdpSocket::bind(local).await.unwrap();
+    socket.connect(locator).await.unwrap();
+    let mut seq: u64 = 0;
+    loop {
+        message[..8].copy_from_slice(&seq.to_le_bytes());
+        // The datagrams refused by a subscriber not yet started are dropped
+        // like on a congested link, only the ones sent are counted
+        if socket.send(&message).await.is_ok() {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+        seq += 1;
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // initiate logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    // The sequence number is written on the first 8 bytes, the length of the
+    // stream messages on 16 bits and the datagrams are bounded by UDP
+    let max = if opt.transport == "udp" {
+        MAX_DATAGRAM
+    } else {
+        u16::MAX as usize
+    };
+    if opt.payload < 8 || opt.payload > max {
+        panic!("Unsupported payload: {}", opt.payload);
+    }
+
+    let count = Arc::new(AtomicUsize::new(0));
+    if opt.print {
+        let c_count = count.clone();
+        task::spawn(async move {
+            loop {
+                task::sleep(Duration::from_secs(1)).await;
+                let c = c_count.swap(0, Ordering::Relaxed);
+                if c > 0 {
+                    println!("{} msg/s", c);
+                }
+            }
+        });
+    }
+
+    // The same payload as zn_pub_thr, the sequence number lets the UDP
+    // subscribers account for the lost messages
+    let bytes = (0usize..opt.payload)
+        .map(|i| (i % 10) as u8)
+        .collect::<Vec<u8>>();
+    match opt.transport.as_str() {
+        "tcp" => {
+            let stream = TcpStream::connect(&opt.locator).await.unwrap();
+            pub_stream(stream, &bytes, &count).await
+        }
+        "udp" => pub_udp(&opt.locator, bytes, &count).await,
+        #[cfg(all(unix, feature = "transport_unixsock-stream"))]
+        "unixsock-stream" => {
+            let stream = UnixStream::connect(&opt.locator).await.unwrap();
+            pub_stream(stream, &bytes, &count).await
+        }
+        _ => panic!("Unsupported transport: {}", opt.transport),
+    }
+}
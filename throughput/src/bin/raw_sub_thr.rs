@@ -0,0 +1,160 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::io;
+use async_std::net::{TcpListener, UdpSocket};
+#[cfg(all(unix, feature = "transport_unixsock-stream"))]
+use async_std::os::unix::net::UnixListener;
+use async_std::prelude::*;
+use async_std::sync::Arc;
+use async_std::task;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "raw_sub_thr")]
+struct Opt {
+    // tcp, udp, or unixsock-stream when built with the
+    // transport_unixsock-stream feature, the locator being the socket path
+    #[structopt(short = "t", long = "transport", default_value = "tcp")]
+    transport: String,
+    #[structopt(short = "l", long = "locator")]
+    locator: String,
+    #[structopt(short = "p", long = "payload")]
+    payload: usize,
+    #[structopt(short = "n", long = "name")]
+    name: String,
+    #[structopt(short = "s", long = "scenario")]
+    scenario: String,
+}
+
+// Count the messages of a publisher, each prefixed by its length on 16 bits
+async fn sub_stream<S: io::Read + Unpin>(mut stream: S, messages: Arc<AtomicUsize>) {
+    let mut buffer = vec![0u8; u16::MAX as usize];
+    loop {
+        let mut length = [0u8; 2];
+        if stream.read_exact(&mut length).await.is_err() {
+            break;
+        }
+        let length = u16::from_le_bytes(length) as usize;
+        if stream.read_exact(&mut buffer[..length]).await.is_err() {
+            break;
+        }
+        messages.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+async fn sub_tcp(locator: &str, messages: Arc<AtomicUsize>) {
+    let listener = TcpListener::bind(locator).await.unwrap();
+    let mut incoming = listener.incoming();
+    while let Some(stream) = incoming.next().await {
+        task::spawn(sub_stream(stream.unwrap(), messages.clone()));
+    }
+}
+
+#[cfg(all(unix, feature = "transport_unixsock-stream"))]
+async fn sub_unix(locator: &str, messages: Arc<AtomicUsize>) {
+    // Remove the socket file left over by a previous run
+    let _ = std::fs::remove_file(locator);
+    let listener = UnixListener::bind(locator).await.unwrap();
+    let mut incoming = listener.incoming();
+    while let Some(stream) = incoming.next().await {
+        task::spawn(sub_stream(stream.unwrap(), messages.clone()));
+    }
+}
+
+// Count the datagrams and the gaps in their sequence numbers
+async fn sub_udp(locator: &str, messages: Arc<AtomicUsize>, lost: Arc<AtomicUsize>) {
+    let socket = UdpSocket::bind(locator).await.unwrap();
+    let mut buffer = vec![0u8; 65_536];
+    let mut next: u64 = 0;
+    loop {
+        let n = socket.recv(&mut buffer).await.unwrap();
+        messages.fetch_add(1, Ordering::Relaxed);
+        if n < 8 {
+            continue;
+        }
+        let mut seq_bytes = [0u8; 8];
+        seq_bytes.copy_from_slice(&buffer[..8]);
+        let seq = u64::from_le_bytes(seq_bytes);
+        // A publisher restarting from zero is not accounted as a loss
+        if seq > next {
+            lost.fetch_add((seq - next) as usize, Ordering::Relaxed);
+        }
+        next = seq + 1;
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // initiate logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    let layer = match opt.transport.as_str() {
+        "tcp" => "raw-tcp",
+        "udp" => "raw-udp",
+        #[cfg(all(unix, feature = "transport_unixsock-stream"))]
+        "unixsock-stream" => "raw-unixsock-stream",
+        _ => panic!("Unsupported transport: {}", opt.transport),
+    };
+
+    let messages = Arc::new(AtomicUsize::new(0));
+    let c_messages = messages.clone();
+    let lost = Arc::new(AtomicUsize::new(0));
+    let c_lost = lost.clone();
+    let lossy = opt.transport == "udp";
+    let scenario = opt.scenario;
+    let name = opt.name;
+    let payload = opt.payload;
+    // The messages are always ready to be read from a fast publisher, the
+    // rate is printed from a thread of its own for the readers not to starve it
+    thread::spawn(move || loop {
+        let now = Instant::now();
+        thread::sleep(Duration::from_secs(1));
+        let elapsed = now.elapsed().as_micros() as f64;
+
+        let c = c_messages.swap(0, Ordering::Relaxed);
+        if c > 0 {
+            println!(
+                "{},{},throughput,{},{},{}",
+                layer,
+                scenario,
+                name,
+                payload,
+                (c as f64 * 1_000_000.0 / elapsed).floor() as usize
+            );
+            if lossy {
+                println!(
+                    "{},{},loss,{},{},{}",
+                    layer,
+                    scenario,
+                    name,
+                    payload,
+                    c_lost.swap(0, Ordering::Relaxed)
+                );
+            }
+        }
+    });
+
+    match opt.transport.as_str() {
+        "udp" => sub_udp(&opt.locator, messages, lost).await,
+        #[cfg(all(unix, feature = "transport_unixsock-stream"))]
+        "unixsock-stream" => sub_unix(&opt.locator, messages).await,
+        _ => sub_tcp(&opt.locator, messages).await,
+    }
+}
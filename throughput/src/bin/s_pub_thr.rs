@@ -24,10 +24,13 @@ use zenoh::net::protocol::link::Locator;
 use zenoh::net::protocol::proto::ZenohMessage;
 use zenoh::net::protocol::session::{
     DummySessionEventHandler, Session, SessionEventHandler, SessionHandler, SessionManager,
-    SessionManagerConfig, SessionManagerOptionalConfig,
+    SessionManagerConfig,
 };
+use zenoh_perf_common::reliability::reliability;
+use zenoh_perf_common::seq::{check_best_effort_payload, stamp};
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::core::ZResult;
-use zenoh_util::properties::{IntKeyProperties, Properties};
+use zenoh_util::properties::Properties;
 
 struct MySH {}
 
@@ -59,6 +62,10 @@ struct Opt {
     print: bool,
     #[structopt(short = "c", long = "conf", parse(from_os_str))]
     config: Option<PathBuf>,
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -69,6 +76,9 @@ async fn main() {
     // Parse the args
     let opt = Opt::from_args();
 
+    let reliability = reliability(&opt.reliability);
+    check_best_effort_payload(reliability, opt.payload);
+
     // Initialize the Peer Id
     let mut pid = [0u8; PeerId::MAX_SIZE];
     rand::thread_rng().fill_bytes(&mut pid);
@@ -77,6 +87,7 @@ async fn main() {
     let whatami = match opt.mode.as_str() {
         "peer" => whatami::PEER,
         "client" => whatami::CLIENT,
+        "router" => whatami::ROUTER,
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
 
@@ -86,30 +97,27 @@ async fn main() {
         id: pid,
         handler: Arc::new(MySH::new()),
     };
-    let opt_config = match opt.config.as_ref() {
+    let properties = match opt.config.as_ref() {
         Some(f) => {
             let config = async_std::fs::read_to_string(f).await.unwrap();
-            let properties = Properties::from(config);
-            let int_props = IntKeyProperties::from(properties);
-            SessionManagerOptionalConfig::from_properties(&int_props)
-                .await
-                .unwrap()
+            Properties::from(config)
         }
-        None => None,
+        None => Properties::default(),
     };
+    let opt_config = opt.tls.manager_config_with(properties).await;
     let manager = SessionManager::new(config, opt_config);
 
     // Connect to publisher
     let session = manager.open_session(&opt.locator).await.unwrap();
 
-    // Send reliable messages
     let channel = Channel {
         priority: Priority::Data,
-        reliability: Reliability::Reliable,
+        reliability,
     };
     let key = ResKey::RId(1);
     let info = None;
-    let payload = ZBuf::from(vec![0u8; opt.payload]);
+    let bytes = vec![0u8; opt.payload];
+    let payload = ZBuf::from(bytes.clone());
     let reply_context = None;
     let routing_context = None;
     let attachment = None;
@@ -127,10 +135,15 @@ async fn main() {
             }
         });
 
+        let mut seq: u64 = 0;
         loop {
+            let payload = match reliability {
+                Reliability::Reliable => payload.clone(),
+                Reliability::BestEffort => stamp(&bytes, seq),
+            };
             let message = ZenohMessage::make_data(
                 key.clone(),
-                payload.clone(),
+                payload,
                 channel,
                 info.clone(),
                 routing_context,
@@ -142,12 +155,18 @@ async fn main() {
                 break;
             }
             c_count.fetch_add(1, Ordering::Relaxed);
+            seq += 1;
         }
     } else {
+        let mut seq: u64 = 0;
         loop {
+            let payload = match reliability {
+                Reliability::Reliable => payload.clone(),
+                Reliability::BestEffort => stamp(&bytes, seq),
+            };
             let message = ZenohMessage::make_data(
                 key.clone(),
-                payload.clone(),
+                payload,
                 channel,
                 info.clone(),
                 routing_context,
@@ -158,6 +177,7 @@ async fn main() {
             if res.is_err() {
                 break;
             }
+            seq += 1;
         }
     }
 }
@@ -16,36 +16,50 @@ use async_std::task;
 use rand::RngCore;
 use std::any::Any;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::time::Duration;
 use structopt::StructOpt;
 use zenoh::net::protocol::core::{whatami, Channel, PeerId, Priority, Reliability, ResKey};
 use zenoh::net::protocol::io::ZBuf;
 use zenoh::net::protocol::link::{Link, Locator};
-use zenoh::net::protocol::proto::ZenohMessage;
+use zenoh::net::protocol::proto::{Data, ZenohBody, ZenohMessage};
 use zenoh::net::protocol::session::{
     Session, SessionEventHandler, SessionHandler, SessionManager, SessionManagerConfig,
-    SessionManagerOptionalConfig,
 };
+use zenoh_perf_common::reliability::reliability;
+use zenoh_perf_common::seq::{account_payload, check_best_effort_payload, stamp};
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::core::ZResult;
-use zenoh_util::properties::{IntKeyProperties, Properties};
+use zenoh_util::properties::Properties;
 
 // Session Handler for the peer
 struct MySH {
     scenario: String,
     name: String,
     payload: usize,
+    best_effort: bool,
     counter: Arc<AtomicUsize>,
+    next: Arc<AtomicU64>,
+    lost: Arc<AtomicUsize>,
     active: AtomicBool,
 }
 
 impl MySH {
-    fn new(scenario: String, name: String, payload: usize, counter: Arc<AtomicUsize>) -> Self {
+    fn new(
+        scenario: String,
+        name: String,
+        payload: usize,
+        best_effort: bool,
+        counter: Arc<AtomicUsize>,
+    ) -> Self {
         Self {
             scenario,
             name,
             payload,
+            best_effort,
             counter,
+            next: Arc::new(AtomicU64::new(0)),
+            lost: Arc::new(AtomicUsize::new(0)),
             active: AtomicBool::new(false),
         }
     }
@@ -58,6 +72,8 @@ impl SessionHandler for MySH {
     ) -> ZResult<Arc<dyn SessionEventHandler + Send + Sync>> {
         if !self.active.swap(true, Ordering::Acquire) {
             let count = self.counter.clone();
+            let lost = self.lost.clone();
+            let best_effort = self.best_effort;
             let scenario = self.scenario.clone();
             let name = self.name.clone();
             let payload = self.payload;
@@ -66,27 +82,54 @@ impl SessionHandler for MySH {
                     task::sleep(Duration::from_secs(1)).await;
                     let c = count.swap(0, Ordering::Relaxed);
                     println!("session,{},throughput,{},{},{}", scenario, name, payload, c);
+                    if best_effort {
+                        let l = lost.swap(0, Ordering::Relaxed);
+                        println!("session,{},loss,{},{},{}", scenario, name, payload, l);
+                    }
                 }
             });
         }
-        Ok(Arc::new(MyMH::new(self.counter.clone())))
+        Ok(Arc::new(MyMH::new(
+            self.best_effort,
+            self.counter.clone(),
+            self.next.clone(),
+            self.lost.clone(),
+        )))
     }
 }
 
 // Message Handler for the peer
 struct MyMH {
+    best_effort: bool,
     counter: Arc<AtomicUsize>,
+    next: Arc<AtomicU64>,
+    lost: Arc<AtomicUsize>,
 }
 
 impl MyMH {
-    fn new(counter: Arc<AtomicUsize>) -> Self {
-        Self { counter }
+    fn new(
+        best_effort: bool,
+        counter: Arc<AtomicUsize>,
+        next: Arc<AtomicU64>,
+        lost: Arc<AtomicUsize>,
+    ) -> Self {
+        Self {
+            best_effort,
+            counter,
+            next,
+            lost,
+        }
     }
 }
 
 impl SessionEventHandler for MyMH {
-    fn handle_message(&self, _message: ZenohMessage) -> ZResult<()> {
+    fn handle_message(&self, message: ZenohMessage) -> ZResult<()> {
         self.counter.fetch_add(1, Ordering::Relaxed);
+        if self.best_effort {
+            if let ZenohBody::Data(Data { mut payload, .. }) = message.body {
+                account_payload(&self.next, &self.lost, &mut payload);
+            }
+        }
         Ok(())
     }
 
@@ -118,6 +161,10 @@ struct Opt {
     print: bool,
     #[structopt(short = "c", long = "conf", parse(from_os_str))]
     config: Option<PathBuf>,
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -128,6 +175,9 @@ async fn main() {
     // Parse the args
     let opt = Opt::from_args();
 
+    let reliability = reliability(&opt.reliability);
+    check_best_effort_payload(reliability, opt.payload);
+
     // Initialize the Peer Id
     let mut pid = [0u8; PeerId::MAX_SIZE];
     rand::thread_rng().fill_bytes(&mut pid);
@@ -136,6 +186,7 @@ async fn main() {
     let whatami = match opt.mode.as_str() {
         "peer" => whatami::PEER,
         "client" => whatami::CLIENT,
+        "router" => whatami::ROUTER,
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
 
@@ -144,19 +195,22 @@ async fn main() {
         version: 0,
         whatami,
         id: pid,
-        handler: Arc::new(MySH::new(opt.scenario, opt.name, opt.payload, count)),
+        handler: Arc::new(MySH::new(
+            opt.scenario,
+            opt.name,
+            opt.payload,
+            reliability == Reliability::BestEffort,
+            count,
+        )),
     };
-    let opt_config = match opt.config.as_ref() {
+    let properties = match opt.config.as_ref() {
         Some(f) => {
             let config = async_std::fs::read_to_string(f).await.unwrap();
-            let properties = Properties::from(config);
-            let int_props = IntKeyProperties::from(properties);
-            SessionManagerOptionalConfig::from_properties(&int_props)
-                .await
-                .unwrap()
+            Properties::from(config)
         }
-        None => None,
+        None => Properties::default(),
     };
+    let opt_config = opt.tls.manager_config_with(properties).await;
     let manager = SessionManager::new(config, opt_config);
 
     // Connect to publisher
@@ -169,27 +223,40 @@ async fn main() {
         }
     };
 
-    // Send reliable messages
     let channel = Channel {
         priority: Priority::Data,
-        reliability: Reliability::Reliable,
+        reliability,
     };
     let key = ResKey::RName("test".to_string());
     let info = None;
-    let payload = ZBuf::from(vec![0u8; opt.payload]);
+    let bytes = vec![0u8; opt.payload];
+    let payload = ZBuf::from(bytes.clone());
     let reply_context = None;
     let routing_context = None;
     let attachment = None;
 
     let message = ZenohMessage::make_data(
-        key,
+        key.clone(),
         payload,
         channel,
-        info,
+        info.clone(),
         routing_context,
-        reply_context,
-        attachment,
+        reply_context.clone(),
+        attachment.clone(),
     );
+    // Best-effort messages carry a sequence number to account for the losses
+    let make_message = |seq: u64| match reliability {
+        Reliability::Reliable => message.clone(),
+        Reliability::BestEffort => ZenohMessage::make_data(
+            key.clone(),
+            stamp(&bytes, seq),
+            channel,
+            info.clone(),
+            routing_context,
+            reply_context.clone(),
+            attachment.clone(),
+        ),
+    };
 
     if opt.print {
         let count = Arc::new(AtomicUsize::new(0));
@@ -204,19 +271,23 @@ async fn main() {
             }
         });
 
+        let mut seq: u64 = 0;
         loop {
-            let res = session.handle_message(message.clone());
+            let res = session.handle_message(make_message(seq));
             if res.is_err() {
                 break;
             }
             c_count.fetch_add(1, Ordering::Relaxed);
+            seq += 1;
         }
     } else {
+        let mut seq: u64 = 0;
         loop {
-            let res = session.handle_message(message.clone());
+            let res = session.handle_message(make_message(seq));
             if res.is_err() {
                 break;
             }
+            seq += 1;
         }
     }
 }
@@ -23,10 +23,10 @@ use zenoh::net::protocol::link::{Link, Locator};
 use zenoh::net::protocol::proto::ZenohMessage;
 use zenoh::net::protocol::session::{
     Session, SessionEventHandler, SessionHandler, SessionManager, SessionManagerConfig,
-    SessionManagerOptionalConfig,
 };
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::core::ZResult;
-use zenoh_util::properties::{IntKeyProperties, Properties};
+use zenoh_util::properties::Properties;
 
 type Table = Arc<Mutex<Slab<Session>>>;
 
@@ -86,8 +86,14 @@ impl SessionEventHandler for MyMH {
 struct Opt {
     #[structopt(short = "l", long = "locator")]
     locator: Vec<Locator>,
+    // The whatami announced to the publishers and subscribers, it listens in
+    // any mode
+    #[structopt(short = "m", long = "mode", default_value = "peer")]
+    mode: String,
     #[structopt(short = "c", long = "conf", parse(from_os_str))]
     config: Option<PathBuf>,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -95,6 +101,13 @@ async fn main() {
     // Parse the args
     let opt = Opt::from_args();
 
+    let whatami = match opt.mode.as_str() {
+        "peer" => whatami::PEER,
+        "client" => whatami::CLIENT,
+        "router" => whatami::ROUTER,
+        _ => panic!("Unsupported mode: {}", opt.mode),
+    };
+
     // Initialize the Peer Id
     let mut pid = [0u8; PeerId::MAX_SIZE];
     rand::thread_rng().fill_bytes(&mut pid);
@@ -103,21 +116,18 @@ async fn main() {
     // Create the session manager
     let config = SessionManagerConfig {
         version: 0,
-        whatami: whatami::PEER,
+        whatami,
         id: pid,
         handler: Arc::new(MySH::new()),
     };
-    let opt_config = match opt.config.as_ref() {
+    let properties = match opt.config.as_ref() {
         Some(f) => {
             let config = async_std::fs::read_to_string(f).await.unwrap();
-            let properties = Properties::from(config);
-            let int_props = IntKeyProperties::from(properties);
-            SessionManagerOptionalConfig::from_properties(&int_props)
-                .await
-                .unwrap()
+            Properties::from(config)
         }
-        None => None,
+        None => Properties::default(),
     };
+    let opt_config = opt.tls.manager_config_with(properties).await;
     let manager = SessionManager::new(config, opt_config);
 
     // Connect to publisher
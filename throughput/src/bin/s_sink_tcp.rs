@@ -11,18 +11,23 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
-use async_std::net::{SocketAddr, TcpListener, TcpStream};
+use async_std::io;
+use async_std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+#[cfg(all(unix, feature = "transport_unixsock-stream"))]
+use async_std::os::unix::net::{UnixListener, UnixStream};
 use async_std::prelude::*;
 use async_std::sync::Arc;
 use async_std::task;
 use rand::RngCore;
 use std::convert::TryInto;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::time::Duration;
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
 use structopt::StructOpt;
 use zenoh::net::protocol::core::{whatami, PeerId};
 use zenoh::net::protocol::io::{WBuf, ZBuf, ZSlice};
 use zenoh::net::protocol::proto::{InitSyn, OpenSyn, SessionBody, SessionMessage};
+use zenoh_perf_overhead::batch::{session_messages, Batches};
+use zenoh_perf_throughput::sink::{Counters, Reassembly, Totals};
 
 macro_rules! zsend {
     ($msg:expr, $stream:expr) => {{
@@ -59,11 +64,44 @@ macro_rules! zrecv {
     }};
 }
 
-async fn handle_client(mut stream: TcpStream) -> Result<(), Box<dyn std::error::Error>> {
+// The streams the sessions are accepted on
+trait Link: io::Read + io::Write + Clone + Unpin + Send + 'static {
+    fn peer(&self) -> io::Result<String>;
+    fn close(&self);
+}
+
+impl Link for TcpStream {
+    fn peer(&self) -> io::Result<String> {
+        Ok(self.peer_addr()?.to_string())
+    }
+
+    fn close(&self) {
+        let _ = self.shutdown(Shutdown::Both);
+    }
+}
+
+#[cfg(all(unix, feature = "transport_unixsock-stream"))]
+impl Link for UnixStream {
+    // The clients are usually not bound to a path
+    fn peer(&self) -> io::Result<String> {
+        Ok(format!("unixsock-stream/{:?}", self.peer_addr()?))
+    }
+
+    fn close(&self) {
+        let _ = self.shutdown(Shutdown::Both);
+    }
+}
+
+async fn handle_client<S: Link>(
+    mut stream: S,
+    counters: Arc<Counters>,
+    decode: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
     let my_whatami = whatami::ROUTER;
     let mut my_pid = [0u8; PeerId::MAX_SIZE];
     rand::thread_rng().fill_bytes(&mut my_pid);
     let my_pid = PeerId::new(1, my_pid);
+    let peer = stream.peer()?;
 
     // Create the reading buffer
     let mut buffer = vec![0u8; 16_000_000];
@@ -92,7 +130,7 @@ async fn handle_client(mut stream: TcpStream) -> Result<(), Box<dyn std::error::
 
     // Read the OpenSyn
     let message = zrecv!(stream, buffer);
-    match &message.body {
+    let lease = match &message.body {
         SessionBody::OpenSyn(OpenSyn {
             lease, initial_sn, ..
         }) => {
@@ -100,49 +138,97 @@ async fn handle_client(mut stream: TcpStream) -> Result<(), Box<dyn std::error::
             let message = SessionMessage::make_open_ack(*lease, *initial_sn, attachment);
             // Send the OpenAck
             let _ = zsend!(message, stream).unwrap();
+            Duration::from_millis(*lease)
         }
         _ => panic!(),
-    }
+    };
+    let start = Instant::now();
 
-    // Spawn the loggin task
-    let counter = Arc::new(AtomicUsize::new(0));
-    let c_c = counter.clone();
+    // Spawn the KeepAlive task, it stops once the session is closed
+    let mut c_stream = stream.clone();
     task::spawn(async move {
         loop {
             task::sleep(Duration::from_secs(1)).await;
-            let c = c_c.swap(0, Ordering::Relaxed);
-            if c > 0 {
-                println!("{:.6} Gbit/s", (8_f64 * c as f64) / 1000000000_f64);
+            let message = SessionMessage::make_keep_alive(None, None);
+            if zsend!(message, c_stream).is_err() {
+                break;
             }
         }
     });
 
-    // Spawn the KeepAlive task
-    let mut c_stream = stream.clone();
-    task::spawn(async move {
-        loop {
-            task::sleep(Duration::from_secs(1)).await;
-            let message = SessionMessage::make_keep_alive(None, None);
-            let _ = zsend!(message, c_stream);
+    // Read from the socket until the session is closed, the peer closes the
+    // link right after a Close message: the batches are always decoded to
+    // catch it, the message-level statistics are only reported when decoding
+    let mut totals = Totals::default();
+    let mut reassembly = Reassembly::default();
+    let mut batches = Batches::default();
+    let reason = 'session: loop {
+        let n = match io::timeout(lease, stream.read(&mut buffer)).await {
+            Ok(0) => break "disconnected",
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::TimedOut => break "lease expired",
+            Err(_) => break "disconnected",
+        };
+        let mut received = Totals {
+            bytes: n,
+            ..Totals::default()
+        };
+        batches.extend(&buffer[..n]);
+        while let Some(batch) = batches.next_batch() {
+            for message in session_messages(batch).iter() {
+                if let SessionBody::Close(_) = &message.body {
+                    counters.add(&received);
+                    totals.add(&received);
+                    break 'session "closed";
+                }
+                received.count(message, &mut reassembly);
+            }
         }
-    });
+        counters.add(&received);
+        totals.add(&received);
+    };
+    stream.close();
+    totals.print_summary(&peer, reason, start.elapsed(), decode);
 
-    // Read from the socket
-    loop {
-        let n = stream.read(&mut buffer).await?;
-        let _ = counter.fetch_add(n, Ordering::Relaxed);
-    }
+    Ok(())
 }
 
-async fn run(addr: SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
-    let locator = TcpListener::bind(addr).await?;
-    let mut incoming = locator.incoming();
+async fn run(
+    transport: &str,
+    locator: &str,
+    decode: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let counters = Arc::new(Counters::default());
+    counters.clone().log(decode);
 
-    while let Some(stream) = incoming.next().await {
-        let stream = stream?;
-        task::spawn(async move {
-            let _ = handle_client(stream).await;
-        });
+    match transport {
+        "tcp" => {
+            let addr: SocketAddr = locator.parse()?;
+            let listener = TcpListener::bind(addr).await?;
+            let mut incoming = listener.incoming();
+            while let Some(stream) = incoming.next().await {
+                let stream = stream?;
+                let c_counters = counters.clone();
+                task::spawn(async move {
+                    let _ = handle_client(stream, c_counters, decode).await;
+                });
+            }
+        }
+        #[cfg(all(unix, feature = "transport_unixsock-stream"))]
+        "unixsock-stream" => {
+            // Remove the socket file left over by a previous run
+            let _ = std::fs::remove_file(locator);
+            let listener = UnixListener::bind(locator).await?;
+            let mut incoming = listener.incoming();
+            while let Some(stream) = incoming.next().await {
+                let stream = stream?;
+                let c_counters = counters.clone();
+                task::spawn(async move {
+                    let _ = handle_client(stream, c_counters, decode).await;
+                });
+            }
+        }
+        _ => panic!("Unsupported transport: {}", transport),
     }
 
     Ok(())
@@ -151,13 +237,20 @@ async fn run(addr: SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
 #[derive(Debug, StructOpt)]
 #[structopt(name = "s_sink_tcp")]
 struct Opt {
+    // tcp, or unixsock-stream when built with the transport_unixsock-stream
+    // feature, the locator being the path of the socket
+    #[structopt(short = "t", long = "transport", default_value = "tcp")]
+    transport: String,
     #[structopt(short = "l", long = "locator")]
-    locator: SocketAddr,
+    locator: String,
+    // Report message-level statistics
+    #[structopt(short = "d", long = "decode")]
+    decode: bool,
 }
 
 #[async_std::main]
 async fn main() {
     env_logger::init();
     let opt = Opt::from_args();
-    let _ = run(opt.locator).await;
+    let _ = run(&opt.transport, &opt.locator, opt.decode).await;
 }
@@ -11,16 +11,18 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
+use async_std::io;
 use async_std::net::{SocketAddr, UdpSocket};
 use async_std::sync::Arc;
-use async_std::task;
 use rand::RngCore;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::time::Duration;
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
 use structopt::StructOpt;
 use zenoh::net::protocol::core::{whatami, PeerId};
 use zenoh::net::protocol::io::{WBuf, ZBuf, ZSlice};
 use zenoh::net::protocol::proto::{InitSyn, OpenSyn, SessionBody, SessionMessage};
+use zenoh_perf_throughput::sink::{Counters, Reassembly, Totals};
 
 macro_rules! zsend {
     ($msg:expr, $socket:expr, $addr:expr) => {{
@@ -37,15 +39,37 @@ macro_rules! zsend {
     }};
 }
 
-macro_rules! zrecv {
-    ($socket:expr, $buffer:expr) => {{
-        let (n, addr) = $socket.recv_from(&mut $buffer).await.unwrap();
-        let mut zbuf = ZBuf::from(&$buffer[..n]);
-        (zbuf.read_session_message().unwrap(), addr)
-    }};
+// A session with a peer, open once its OpenSyn is received
+struct Session {
+    open: bool,
+    lease: Duration,
+    start: Instant,
+    last: Instant,
+    totals: Totals,
+    reassembly: Reassembly,
+}
+
+impl Session {
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            open: false,
+            lease: Duration::from_secs(10),
+            start: now,
+            last: now,
+            totals: Totals::default(),
+            reassembly: Reassembly::default(),
+        }
+    }
 }
 
-async fn handle_client(socket: Arc<UdpSocket>) -> Result<(), Box<dyn std::error::Error>> {
+// Serve all the peers on a single socket, demultiplexed by address: every
+// datagram is decoded to follow the lifecycle of its session
+async fn handle_clients(
+    socket: Arc<UdpSocket>,
+    counters: Arc<Counters>,
+    decode: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
     let my_whatami = whatami::ROUTER;
     let mut my_pid = [0u8; PeerId::MAX_SIZE];
     rand::thread_rng().fill_bytes(&mut my_pid);
@@ -54,82 +78,131 @@ async fn handle_client(socket: Arc<UdpSocket>) -> Result<(), Box<dyn std::error:
     // Create the reading buffer
     let mut buffer = vec![0u8; 16_000_000];
 
-    // Read the InitSyn
-    let (message, addr) = zrecv!(socket, buffer);
-    match &message.body {
-        SessionBody::InitSyn(InitSyn { is_qos, .. }) => {
-            let whatami = my_whatami;
-            let sn_resolution = None;
-            let cookie = ZSlice::from(vec![0u8; 8]);
-            let attachment = None;
-            let message = SessionMessage::make_init_ack(
-                whatami,
-                my_pid.clone(),
-                sn_resolution,
-                *is_qos,
-                cookie,
-                attachment,
-            );
-            // Send the InitAck
-            let _ = zsend!(message, socket, addr).unwrap();
+    let mut sessions: HashMap<SocketAddr, Session> = HashMap::new();
+    let period = Duration::from_secs(1);
+    let mut last_keep_alive = Instant::now();
+    loop {
+        // Periodically send the KeepAlives and expire the silent sessions
+        if last_keep_alive.elapsed() >= period {
+            last_keep_alive = Instant::now();
+            let expired: Vec<SocketAddr> = sessions
+                .iter()
+                .filter(|(_, session)| session.last.elapsed() > session.lease)
+                .map(|(addr, _)| *addr)
+                .collect();
+            for addr in expired {
+                let session = sessions.remove(&addr).unwrap();
+                session.totals.print_summary(
+                    addr,
+                    "lease expired",
+                    session.start.elapsed(),
+                    decode,
+                );
+            }
+            for (addr, session) in sessions.iter() {
+                if session.open {
+                    let message = SessionMessage::make_keep_alive(None, None);
+                    let _ = zsend!(message, socket, *addr);
+                }
+            }
         }
-        _ => panic!(),
-    }
 
-    // Read the OpenSyn
-    let (message, a) = zrecv!(socket, buffer);
-    if a != addr {
-        panic!("Received data from {}, expected from {}", a, addr);
-    }
-    match &message.body {
-        SessionBody::OpenSyn(OpenSyn {
-            lease, initial_sn, ..
-        }) => {
-            let attachment = None;
-            let message = SessionMessage::make_open_ack(*lease, *initial_sn, attachment);
-            // Send the OpenAck
-            let _ = zsend!(message, socket, addr).unwrap();
-        }
-        _ => panic!(),
-    }
+        let (n, addr) = match io::timeout(period, socket.recv_from(&mut buffer)).await {
+            Ok(res) => res,
+            Err(e) if e.kind() == ErrorKind::TimedOut => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        // Each datagram carries unframed session messages
+        let mut received = Totals {
+            bytes: n,
+            ..Totals::default()
+        };
+        let mut zbuf = ZBuf::from(buffer[..n].to_vec());
+        let mut closed = false;
+        while let Some(message) = zbuf.read_session_message() {
+            match &message.body {
+                SessionBody::InitSyn(InitSyn { is_qos, .. }) => {
+                    // A new session, or a peer restarting its own
+                    if let Some(session) = sessions.remove(&addr) {
+                        session.totals.print_summary(
+                            addr,
+                            "reopened",
+                            session.start.elapsed(),
+                            decode,
+                        );
+                    }
+                    sessions.insert(addr, Session::new());
+
+                    let whatami = my_whatami;
+                    let sn_resolution = None;
+                    let cookie = ZSlice::from(vec![0u8; 8]);
+                    let attachment = None;
+                    let message = SessionMessage::make_init_ack(
+                        whatami,
+                        my_pid.clone(),
+                        sn_resolution,
+                        *is_qos,
+                        cookie,
+                        attachment,
+                    );
+                    // Send the InitAck
+                    let _ = zsend!(message, socket, addr);
+                }
+                SessionBody::OpenSyn(OpenSyn {
+                    lease, initial_sn, ..
+                }) => {
+                    if let Some(session) = sessions.get_mut(&addr) {
+                        session.open = true;
+                        session.lease = Duration::from_millis(*lease);
+                        session.start = Instant::now();
 
-    // Spawn the loggin task
-    let counter = Arc::new(AtomicUsize::new(0));
-    let c_c = counter.clone();
-    task::spawn(async move {
-        loop {
-            task::sleep(Duration::from_secs(1)).await;
-            let c = c_c.swap(0, Ordering::Relaxed);
-            if c > 0 {
-                println!("{:.6} Gbit/s", (8_f64 * c as f64) / 1000000000_f64);
+                        let attachment = None;
+                        let message =
+                            SessionMessage::make_open_ack(*lease, *initial_sn, attachment);
+                        // Send the OpenAck
+                        let _ = zsend!(message, socket, addr);
+                    }
+                }
+                SessionBody::Close(_) => {
+                    closed = true;
+                    break;
+                }
+                // The datagrams of unknown peers are ignored further on
+                _ => match sessions.get_mut(&addr) {
+                    Some(session) => received.count(&message, &mut session.reassembly),
+                    None => received.count(&message, &mut Reassembly::default()),
+                },
             }
         }
-    });
 
-    // Spawn the KeepAlive task
-    let c_socket = socket.clone();
-    let c_addr = addr;
-    task::spawn(async move {
-        loop {
-            task::sleep(Duration::from_secs(1)).await;
-            let message = SessionMessage::make_keep_alive(None, None);
-            let _ = zsend!(message, c_socket, c_addr).unwrap();
+        let session = match sessions.get_mut(&addr) {
+            Some(session) => session,
+            None => {
+                log::debug!("Ignoring a datagram from unknown peer {}", addr);
+                continue;
+            }
+        };
+        session.last = Instant::now();
+        if session.open {
+            counters.add(&received);
+            session.totals.add(&received);
         }
-    });
-
-    // Read from the socket
-    loop {
-        let (n, a) = socket.recv_from(&mut buffer).await.unwrap();
-        if a != addr {
-            panic!("Received data from {}, expected from {}", a, addr);
+        if closed {
+            let session = sessions.remove(&addr).unwrap();
+            session
+                .totals
+                .print_summary(addr, "closed", session.start.elapsed(), decode);
         }
-        let _ = counter.fetch_add(n, Ordering::Relaxed);
     }
 }
 
-async fn run(addr: SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
+async fn run(addr: SocketAddr, decode: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let counters = Arc::new(Counters::default());
+    counters.clone().log(decode);
+
     let socket = UdpSocket::bind(addr).await?;
-    handle_client(Arc::new(socket)).await
+    handle_clients(Arc::new(socket), counters, decode).await
 }
 
 #[derive(Debug, StructOpt)]
@@ -137,11 +210,14 @@ async fn run(addr: SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
 struct Opt {
     #[structopt(short = "l", long = "locator")]
     locator: SocketAddr,
+    // Report message-level statistics
+    #[structopt(short = "d", long = "decode")]
+    decode: bool,
 }
 
 #[async_std::main]
 async fn main() {
     env_logger::init();
     let opt = Opt::from_args();
-    let _ = run(opt.locator).await;
+    let _ = run(opt.locator, opt.decode).await;
 }
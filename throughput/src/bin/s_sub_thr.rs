@@ -17,35 +17,49 @@ use async_std::task;
 use rand::RngCore;
 use std::any::Any;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
-use zenoh::net::protocol::core::{whatami, PeerId};
+use zenoh::net::protocol::core::{whatami, PeerId, Reliability};
 use zenoh::net::protocol::link::{Link, Locator};
-use zenoh::net::protocol::proto::ZenohMessage;
+use zenoh::net::protocol::proto::{Data, ZenohBody, ZenohMessage};
 use zenoh::net::protocol::session::{
     Session, SessionEventHandler, SessionHandler, SessionManager, SessionManagerConfig,
-    SessionManagerOptionalConfig,
 };
+use zenoh_perf_common::reliability::reliability;
+use zenoh_perf_common::seq::account_payload;
+use zenoh_perf_tls::TlsOpt;
 use zenoh_util::core::ZResult;
-use zenoh_util::properties::{IntKeyProperties, Properties};
+use zenoh_util::properties::Properties;
 
 // Session Handler for the peer
 struct MySH {
     scenario: String,
     name: String,
     payload: usize,
+    best_effort: bool,
     counter: Arc<AtomicUsize>,
+    next: Arc<AtomicU64>,
+    lost: Arc<AtomicUsize>,
     active: AtomicBool,
 }
 
 impl MySH {
-    fn new(scenario: String, name: String, payload: usize, counter: Arc<AtomicUsize>) -> Self {
+    fn new(
+        scenario: String,
+        name: String,
+        payload: usize,
+        best_effort: bool,
+        counter: Arc<AtomicUsize>,
+    ) -> Self {
         Self {
             scenario,
             name,
             payload,
+            best_effort,
             counter,
+            next: Arc::new(AtomicU64::new(0)),
+            lost: Arc::new(AtomicUsize::new(0)),
             active: AtomicBool::new(false),
         }
     }
@@ -58,6 +72,8 @@ impl SessionHandler for MySH {
     ) -> ZResult<Arc<dyn SessionEventHandler + Send + Sync>> {
         if !self.active.swap(true, Ordering::Acquire) {
             let count = self.counter.clone();
+            let lost = self.lost.clone();
+            let best_effort = self.best_effort;
             let scenario = self.scenario.clone();
             let name = self.name.clone();
             let payload = self.payload;
@@ -77,28 +93,60 @@ impl SessionHandler for MySH {
                             payload,
                             (c as f64 / interval).floor() as usize
                         );
+                        if best_effort {
+                            println!(
+                                "session,{},loss,{},{},{}",
+                                scenario,
+                                name,
+                                payload,
+                                lost.swap(0, Ordering::Relaxed)
+                            );
+                        }
                     }
                 }
             });
         }
-        Ok(Arc::new(MyMH::new(self.counter.clone())))
+        Ok(Arc::new(MyMH::new(
+            self.best_effort,
+            self.counter.clone(),
+            self.next.clone(),
+            self.lost.clone(),
+        )))
     }
 }
 
 // Message Handler for the peer
 struct MyMH {
+    best_effort: bool,
     counter: Arc<AtomicUsize>,
+    next: Arc<AtomicU64>,
+    lost: Arc<AtomicUsize>,
 }
 
 impl MyMH {
-    fn new(counter: Arc<AtomicUsize>) -> Self {
-        Self { counter }
+    fn new(
+        best_effort: bool,
+        counter: Arc<AtomicUsize>,
+        next: Arc<AtomicU64>,
+        lost: Arc<AtomicUsize>,
+    ) -> Self {
+        Self {
+            best_effort,
+            counter,
+            next,
+            lost,
+        }
     }
 }
 
 impl SessionEventHandler for MyMH {
-    fn handle_message(&self, _message: ZenohMessage) -> ZResult<()> {
+    fn handle_message(&self, message: ZenohMessage) -> ZResult<()> {
         self.counter.fetch_add(1, Ordering::Relaxed);
+        if self.best_effort {
+            if let ZenohBody::Data(Data { mut payload, .. }) = message.body {
+                account_payload(&self.next, &self.lost, &mut payload);
+            }
+        }
         Ok(())
     }
 
@@ -127,6 +175,10 @@ struct Opt {
     scenario: String,
     #[structopt(short = "c", long = "conf", parse(from_os_str))]
     config: Option<PathBuf>,
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -140,6 +192,7 @@ async fn main() {
     let whatami = match opt.mode.as_str() {
         "peer" => whatami::PEER,
         "client" => whatami::CLIENT,
+        "router" => whatami::ROUTER,
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
 
@@ -148,27 +201,34 @@ async fn main() {
     rand::thread_rng().fill_bytes(&mut pid);
     let pid = PeerId::new(1, pid);
 
+    // The reliability is selected by the publisher: the subscriber only needs
+    // to know whether to account for the lost messages
+    let best_effort = reliability(&opt.reliability) == Reliability::BestEffort;
+
     let count = Arc::new(AtomicUsize::new(0));
     let config = SessionManagerConfig {
         version: 0,
         whatami,
         id: pid,
-        handler: Arc::new(MySH::new(opt.scenario, opt.name, opt.payload, count)),
+        handler: Arc::new(MySH::new(
+            opt.scenario,
+            opt.name,
+            opt.payload,
+            best_effort,
+            count,
+        )),
     };
-    let opt_config = match opt.config.as_ref() {
+    let properties = match opt.config.as_ref() {
         Some(f) => {
             let config = async_std::fs::read_to_string(f).await.unwrap();
-            let properties = Properties::from(config);
-            let int_props = IntKeyProperties::from(properties);
-            SessionManagerOptionalConfig::from_properties(&int_props)
-                .await
-                .unwrap()
+            Properties::from(config)
         }
-        None => None,
+        None => Properties::default(),
     };
+    let opt_config = opt.tls.manager_config_with(properties).await;
     let manager = SessionManager::new(config, opt_config);
 
-    if whatami == whatami::PEER {
+    if whatami != whatami::CLIENT {
         // Connect to the peer or listen
         manager.add_listener(&opt.locator).await.unwrap();
     } else {
@@ -21,6 +21,8 @@ use structopt::StructOpt;
 use zenoh::net::ZBuf;
 use zenoh::Properties;
 use zenoh::*;
+use zenoh_perf_common::router;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "z_put_thr")]
@@ -35,6 +37,8 @@ struct Opt {
     print: bool,
     #[structopt(short = "c", long = "conf", parse(from_os_str))]
     config: Option<PathBuf>,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -55,6 +59,10 @@ async fn main() {
     config.insert("mode".to_string(), opt.mode.clone());
     config.insert("add_timestamp".to_string(), "false".to_string());
 
+    opt.tls.configure(&mut config);
+
+    router::configure(&opt.mode, &mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     config.insert("peer".to_string(), opt.locator);
 
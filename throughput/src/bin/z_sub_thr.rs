@@ -20,6 +20,7 @@ use std::time::{Duration, Instant};
 use structopt::StructOpt;
 use zenoh::Properties;
 use zenoh::*;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "z_sub_thr")]
@@ -36,6 +37,8 @@ struct Opt {
     scenario: String,
     #[structopt(short = "c", long = "conf", parse(from_os_str))]
     config: Option<PathBuf>,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -55,9 +58,11 @@ async fn main() {
     };
     config.insert("mode".to_string(), opt.mode.clone());
 
+    opt.tls.configure(&mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     match opt.mode.as_str() {
-        "peer" => config.insert("listener".to_string(), opt.locator),
+        "peer" | "router" => config.insert("listener".to_string(), opt.locator),
         "client" => config.insert("peer".to_string(), opt.locator),
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
@@ -20,6 +20,10 @@ use structopt::StructOpt;
 use zenoh::net::ResKey::*;
 use zenoh::net::*;
 use zenoh::Properties;
+use zenoh_perf_common::reliability::{congestion_control, lossy, reliability};
+use zenoh_perf_common::router;
+use zenoh_perf_common::seq::{check_payload, stamp};
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "zn_pub_thr")]
@@ -34,6 +38,15 @@ struct Opt {
     print: bool,
     #[structopt(short = "c", long = "conf", parse(from_os_str))]
     config: Option<PathBuf>,
+    /// reliable or best-effort. The subscribers select the reliability, a
+    /// best-effort publisher only stamps the sequence numbers they need to
+    /// account for the lost messages
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(long = "congestion", default_value = "block")]
+    congestion: String,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -54,6 +67,10 @@ async fn main() {
     config.insert("mode".to_string(), opt.mode.clone());
     config.insert("add_timestamp".to_string(), "false".to_string());
 
+    opt.tls.configure(&mut config);
+
+    router::configure(&opt.mode, &mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     config.insert("peer".to_string(), opt.locator);
 
@@ -65,49 +82,80 @@ async fn main() {
         .unwrap());
     let _publ = session.declare_publisher(&reskey).await.unwrap();
 
-    let data: ZBuf = (0usize..opt.payload)
+    // The zenoh-net API does not expose the reliability of a publication: it
+    // is the subscriber that selects it. Publishers only stamp the sequence
+    // numbers needed by best-effort subscribers to account for the losses.
+    let reliability = reliability(&opt.reliability);
+    let congestion_control = congestion_control(&opt.congestion);
+    // Subscribers account for the messages dropped on congestion
+    let stamped = lossy(reliability, congestion_control);
+    if stamped {
+        check_payload(opt.payload);
+    }
+    let bytes = (0usize..opt.payload)
         .map(|i| (i % 10) as u8)
-        .collect::<Vec<u8>>()
-        .into();
+        .collect::<Vec<u8>>();
+    let data: ZBuf = bytes.clone().into();
 
     if opt.print {
         let count = Arc::new(AtomicUsize::new(0));
         let c_count = count.clone();
+        let early = Arc::new(AtomicUsize::new(0));
+        let c_early = early.clone();
         task::spawn(async move {
             loop {
                 task::sleep(Duration::from_secs(1)).await;
                 let c = count.swap(0, Ordering::Relaxed);
+                let e = early.swap(0, Ordering::Relaxed);
                 if c > 0 {
-                    println!("{} msg/s", c);
+                    println!("{} msg/s, {} returned early", c, e);
                 }
             }
         });
 
+        let mut seq: u64 = 0;
         loop {
-            session
+            let data = if stamped {
+                stamp(&bytes, seq)
+            } else {
+                data.clone()
+            };
+            let res = session
                 .write_ext(
                     &reskey,
-                    data.clone(),
+                    data,
                     encoding::DEFAULT,
                     data_kind::DEFAULT,
-                    CongestionControl::Block, // Make sure to not drop messages because of congestion control
+                    congestion_control,
                 )
-                .await
-                .unwrap();
+                .await;
+            // A write that returns early with an error is counted as such,
+            // the messages dropped further on are accounted by the
+            // subscribers from the gap in the sequence numbers
+            if res.is_err() {
+                c_early.fetch_add(1, Ordering::Relaxed);
+            }
             c_count.fetch_add(1, Ordering::Relaxed);
+            seq += 1;
         }
     } else {
+        let mut seq: u64 = 0;
         loop {
-            session
+            let data = if stamped {
+                stamp(&bytes, seq)
+            } else {
+                data.clone()
+            };
+            let _ = session
                 .write_ext(
                     &reskey,
-                    data.clone(),
+                    data,
                     encoding::DEFAULT,
                     data_kind::DEFAULT,
-                    CongestionControl::Block, // Make sure to not drop messages because of congestion control
+                    congestion_control,
                 )
-                .await
-                .unwrap();
+                .await;
+            seq += 1;
         }
     }
 }
@@ -0,0 +1,122 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::future;
+use async_std::net::{TcpListener, TcpStream};
+use async_std::prelude::*;
+use async_std::task;
+use std::fmt::Write;
+use std::time::Instant;
+use structopt::StructOpt;
+use zenoh::net::protocol::core::whatami;
+use zenoh::net::runtime::Runtime;
+use zenoh_perf_common::memory::resident_memory;
+use zenoh_perf_tls::TlsOpt;
+use zenoh_util::properties::config::{
+    ConfigProperties, ZN_LISTENER_KEY, ZN_MODE_KEY, ZN_MULTICAST_SCOUTING_KEY,
+};
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "zn_router")]
+struct Opt {
+    // The locators to listen on, the clients and peers of the benchmarks
+    // connect to them
+    #[structopt(short = "l", long = "locator")]
+    locator: Vec<String>,
+    // The address of the stats endpoint, e.g. 127.0.0.1:8000: an HTTP GET
+    // returns the uptime, the resident memory and the sessions of the router
+    #[structopt(long = "stats")]
+    stats: Option<String>,
+    #[structopt(flatten)]
+    tls: TlsOpt,
+}
+
+// The stats of the router, one comma-separated record per line
+async fn stats(runtime: &Runtime, start: Instant) -> String {
+    let sessions = runtime.manager().get_sessions().await;
+    let mut body = String::new();
+    writeln!(body, "uptime,{}", start.elapsed().as_micros()).unwrap();
+    writeln!(body, "memory,{}", resident_memory()).unwrap();
+    writeln!(body, "sessions,{}", sessions.len()).unwrap();
+    for session in sessions.iter() {
+        let (pid, whatami, links) = match (
+            session.get_pid(),
+            session.get_whatami(),
+            session.get_links(),
+        ) {
+            (Ok(pid), Ok(whatami), Ok(links)) => (pid, whatami, links),
+            // The session has been closed in the meantime
+            _ => continue,
+        };
+        writeln!(
+            body,
+            "session,{},{},{}",
+            pid,
+            whatami::to_string(whatami),
+            links.len()
+        )
+        .unwrap();
+    }
+    body
+}
+
+async fn serve(mut stream: TcpStream, runtime: Runtime, start: Instant) {
+    // Any request gets the stats, it is only read for the client not to see
+    // its connection reset
+    let mut buffer = [0u8; 1024];
+    if stream.read(&mut buffer).await.is_err() {
+        return;
+    }
+    let body = stats(&runtime, start).await;
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+#[async_std::main]
+async fn main() {
+    // Enable logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    let mut config = ConfigProperties::default();
+    config.insert(ZN_MODE_KEY, "router".to_string());
+
+    opt.tls.configure_runtime(&mut config);
+
+    config.insert(ZN_MULTICAST_SCOUTING_KEY, "false".to_string());
+    if !opt.locator.is_empty() {
+        config.insert(ZN_LISTENER_KEY, opt.locator.join(","));
+    }
+
+    let start = Instant::now();
+    let runtime = Runtime::new(0u8, config, None).await.unwrap();
+
+    if let Some(address) = opt.stats.as_ref() {
+        let listener = TcpListener::bind(address).await.unwrap();
+        let mut incoming = listener.incoming();
+        while let Some(stream) = incoming.next().await {
+            if let Ok(stream) = stream {
+                task::spawn(serve(stream, runtime.clone(), start));
+            }
+        }
+    }
+
+    // Stop forever
+    future::pending::<()>().await;
+}
@@ -0,0 +1,132 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::sync::Arc;
+use async_std::task;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use structopt::StructOpt;
+use zenoh::net::ResKey::*;
+use zenoh::net::*;
+use zenoh::Properties;
+use zenoh_perf_common::router;
+use zenoh_perf_tls::TlsOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "zn_shm_pub_thr")]
+struct Opt {
+    #[structopt(short = "l", long = "locator")]
+    locator: String,
+    #[structopt(short = "m", long = "mode")]
+    mode: String,
+    #[structopt(short = "p", long = "payload")]
+    payload: usize,
+    #[structopt(short = "t", long = "print")]
+    print: bool,
+    #[structopt(short = "c", long = "conf", parse(from_os_str))]
+    config: Option<PathBuf>,
+    // The number of payloads the shared memory segment can hold at once, the
+    // publisher waits for the subscribers to release them when it is full
+    #[structopt(long = "buffers", default_value = "8")]
+    buffers: usize,
+    #[structopt(flatten)]
+    tls: TlsOpt,
+}
+
+// Allocate a buffer in the shared memory segment, reclaiming the buffers
+// released by the subscribers when the segment is full
+async fn alloc(shm: &mut SharedMemoryManager, size: usize) -> SharedMemoryBuf {
+    loop {
+        if let Some(sbuf) = shm.alloc(size) {
+            return sbuf;
+        }
+        if shm.garbage_collect() > 0 {
+            shm.defragment();
+        } else {
+            task::yield_now().await;
+        }
+    }
+}
+
+#[async_std::main]
+async fn main() {
+    // initiate logging
+    env_logger::init();
+
+    // Parse the args
+    let opt = Opt::from_args();
+
+    if opt.buffers == 0 {
+        panic!("Unsupported buffers: {}", opt.buffers);
+    }
+
+    let mut config = match opt.config.as_ref() {
+        Some(f) => {
+            let config = async_std::fs::read_to_string(f).await.unwrap();
+            Properties::from(config)
+        }
+        None => Properties::default(),
+    };
+    config.insert("mode".to_string(), opt.mode.clone());
+    config.insert("add_timestamp".to_string(), "false".to_string());
+
+    opt.tls.configure(&mut config);
+
+    router::configure(&opt.mode, &mut config);
+
+    config.insert("multicast_scouting".to_string(), "false".to_string());
+    config.insert("peer".to_string(), opt.locator);
+
+    let session = open(config.into()).await.unwrap();
+
+    // The segment is named after the session, the subscribers on the same
+    // host map it and read the payloads in place
+    let id = session.id().await;
+    let mut shm = SharedMemoryManager::new(id, opt.payload * opt.buffers).unwrap();
+
+    let reskey = RId(session
+        .declare_resource(&RName("/test/thr".to_string()))
+        .await
+        .unwrap());
+    let _publ = session.declare_publisher(&reskey).await.unwrap();
+
+    // The same payload as zn_pub_thr, written in place in the shared memory
+    // instead of being copied into the batches
+    let bytes = (0usize..opt.payload)
+        .map(|i| (i % 10) as u8)
+        .collect::<Vec<u8>>();
+
+    let count = Arc::new(AtomicUsize::new(0));
+    if opt.print {
+        let c_count = count.clone();
+        task::spawn(async move {
+            loop {
+                task::sleep(Duration::from_secs(1)).await;
+                let c = c_count.swap(0, Ordering::Relaxed);
+                if c > 0 {
+                    println!("{} msg/s", c);
+                }
+            }
+        });
+    }
+
+    loop {
+        let mut sbuf = alloc(&mut shm, opt.payload).await;
+        let slice = unsafe { sbuf.as_mut_slice() };
+        slice.copy_from_slice(&bytes);
+
+        session.write(&reskey, sbuf.into()).await.unwrap();
+        count.fetch_add(1, Ordering::Relaxed);
+    }
+}
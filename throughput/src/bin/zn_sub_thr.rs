@@ -11,15 +11,21 @@
 // Contributors:
 //   ADLINK zenoh team, <zenoh@adlink-labs.tech>
 //
+use async_std::future;
 use async_std::sync::Arc;
 use async_std::task;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 use zenoh::net::ResKey::*;
 use zenoh::net::*;
 use zenoh::Properties;
+use zenoh_perf_common::memory::resident_memory;
+use zenoh_perf_common::reliability::{congestion_control, lossy, reliability};
+use zenoh_perf_common::seq::account_payload;
+use zenoh_perf_common::sub::SubOpt;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "zn_sub_thr")]
@@ -36,6 +42,17 @@ struct Opt {
     scenario: String,
     #[structopt(short = "c", long = "conf", parse(from_os_str))]
     config: Option<PathBuf>,
+    #[structopt(flatten)]
+    sub: SubOpt,
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(long = "congestion", default_value = "block")]
+    congestion: String,
+    // Print the resident memory of the whole process every second
+    #[structopt(long = "memory")]
+    memory: bool,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -55,9 +72,11 @@ async fn main() {
     };
     config.insert("mode".to_string(), opt.mode.clone());
 
+    opt.tls.configure(&mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     match opt.mode.as_str() {
-        "peer" => config.insert("listener".to_string(), opt.locator),
+        "peer" | "router" => config.insert("listener".to_string(), opt.locator),
         "client" => config.insert("peer".to_string(), opt.locator),
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
@@ -69,36 +88,99 @@ async fn main() {
         .await
         .unwrap());
 
+    let mode = opt.sub.mode();
+    let period = opt.sub.period();
+    let kind = opt.sub.kind("throughput");
+
+    let reliability = reliability(&opt.reliability);
+    let lossy = lossy(reliability, congestion_control(&opt.congestion));
+
     let messages = Arc::new(AtomicUsize::new(0));
     let c_messages = messages.clone();
+    let lost = Arc::new(AtomicUsize::new(0));
+    let c_lost = lost.clone();
+    let scenario = opt.scenario;
+    let name = opt.name;
+    let payload = opt.payload;
+    let memory = opt.memory;
+    task::spawn(async move {
+        loop {
+            let now = Instant::now();
+            task::sleep(Duration::from_secs(1)).await;
+            let elapsed = now.elapsed().as_micros() as f64;
+
+            let c = c_messages.swap(0, Ordering::Relaxed);
+            if c > 0 {
+                let interval = 1_000_000.0 / elapsed;
+                println!(
+                    "zenoh-net,{},{},{},{},{}",
+                    scenario,
+                    kind,
+                    name,
+                    payload,
+                    (c as f64 / interval).floor() as usize
+                );
+                if lossy {
+                    println!(
+                        "zenoh-net,{},loss,{},{},{}",
+                        scenario,
+                        name,
+                        payload,
+                        c_lost.swap(0, Ordering::Relaxed)
+                    );
+                }
+                if memory {
+                    println!(
+                        "zenoh-net,{},memory,{},{},{}",
+                        scenario,
+                        name,
+                        payload,
+                        resident_memory()
+                    );
+                }
+            }
+        }
+    });
 
     let sub_info = SubInfo {
-        reliability: Reliability::Reliable,
-        mode: SubMode::Push,
-        period: None,
+        reliability,
+        mode,
+        period,
+    };
+    let next = AtomicU64::new(0);
+    let on_sample = move |mut sample: Sample| {
+        messages.fetch_add(1, Ordering::Relaxed);
+        if lossy {
+            account_payload(&next, &lost, &mut sample.payload);
+        }
     };
+
+    if sub_info.mode == SubMode::Pull {
+        // Pull the available samples at the given cadence and take at most
+        // --max-samples of them, the others wait for the next pull
+        let mut sub = session
+            .declare_subscriber(&reskey, &sub_info)
+            .await
+            .unwrap();
+        let sleep = opt.sub.pull_interval();
+        let samples = opt.sub.samples_per_pull();
+        loop {
+            sub.pull().await.unwrap();
+            task::sleep(sleep).await;
+            for _ in 0..samples {
+                match sub.receiver().try_recv() {
+                    Ok(sample) => on_sample(sample),
+                    Err(_) => break,
+                }
+            }
+        }
+    }
+
     let _sub = session
-        .declare_callback_subscriber(&reskey, &sub_info, move |_sample| {
-            c_messages.fetch_add(1, Ordering::Relaxed);
-        })
+        .declare_callback_subscriber(&reskey, &sub_info, on_sample)
         .await
         .unwrap();
 
-    loop {
-        let now = Instant::now();
-        task::sleep(Duration::from_secs(1)).await;
-        let elapsed = now.elapsed().as_micros() as f64;
-
-        let c = messages.swap(0, Ordering::Relaxed);
-        if c > 0 {
-            let interval = 1_000_000.0 / elapsed;
-            println!(
-                "zenoh-net,{},throughput,{},{},{}",
-                opt.scenario,
-                opt.name,
-                opt.payload,
-                (c as f64 / interval).floor() as usize
-            );
-        }
-    }
+    // Stop forever
+    future::pending::<()>().await;
 }
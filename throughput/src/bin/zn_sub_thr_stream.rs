@@ -14,12 +14,15 @@
 use async_std::sync::Arc;
 use async_std::task;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 use zenoh::net::ResKey::*;
 use zenoh::net::*;
 use zenoh::Properties;
+use zenoh_perf_common::reliability::{congestion_control, lossy, reliability};
+use zenoh_perf_common::seq::account_payload;
+use zenoh_perf_tls::TlsOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "zn_sub_thr_stream")]
@@ -36,6 +39,12 @@ struct Opt {
     scenario: String,
     #[structopt(short = "c", long = "conf", parse(from_os_str))]
     config: Option<PathBuf>,
+    #[structopt(long = "reliability", default_value = "reliable")]
+    reliability: String,
+    #[structopt(long = "congestion", default_value = "block")]
+    congestion: String,
+    #[structopt(flatten)]
+    tls: TlsOpt,
 }
 
 #[async_std::main]
@@ -55,10 +64,12 @@ async fn main() {
     };
     config.insert("mode".to_string(), opt.mode.clone());
 
+    opt.tls.configure(&mut config);
+
     config.insert("multicast_scouting".to_string(), "false".to_string());
     let loc = opt.locator.clone();
     match opt.mode.as_str() {
-        "peer" => config.insert("listener".to_string(), loc),
+        "peer" | "router" => config.insert("listener".to_string(), loc),
         "client" => config.insert("peer".to_string(), loc),
         _ => panic!("Unsupported mode: {}", opt.mode),
     };
@@ -70,8 +81,13 @@ async fn main() {
         .await
         .unwrap());
 
+    let reliability = reliability(&opt.reliability);
+    let lossy = lossy(reliability, congestion_control(&opt.congestion));
+
     let messages = Arc::new(AtomicUsize::new(0));
     let c_messages = messages.clone();
+    let lost = Arc::new(AtomicUsize::new(0));
+    let c_lost = lost.clone();
     task::spawn(async move {
         loop {
             let now = Instant::now();
@@ -88,12 +104,21 @@ async fn main() {
                     opt.payload,
                     (c as f64 / interval).floor() as usize
                 );
+                if lossy {
+                    println!(
+                        "zenoh-net,{},loss,{},{},{}",
+                        opt.scenario,
+                        opt.name,
+                        opt.payload,
+                        c_lost.swap(0, Ordering::Relaxed)
+                    );
+                }
             }
         }
     });
 
     let sub_info = SubInfo {
-        reliability: Reliability::Reliable,
+        reliability,
         mode: SubMode::Push,
         period: None,
     };
@@ -102,7 +127,11 @@ async fn main() {
         .await
         .unwrap();
 
-    while sub.receiver().recv().is_ok() {
+    let next = AtomicU64::new(0);
+    while let Ok(mut sample) = sub.receiver().recv() {
         messages.fetch_add(1, Ordering::Relaxed);
+        if lossy {
+            account_payload(&next, &lost, &mut sample.payload);
+        }
     }
 }
@@ -0,0 +1,191 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use async_std::sync::Arc;
+use async_std::task;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use zenoh::net::protocol::proto::{
+    Data, Frame, FramePayload, SessionBody, SessionMessage, ZenohBody,
+};
+
+// The counters of all the sessions of a sink, logged every second
+#[derive(Default)]
+pub struct Counters {
+    bytes: AtomicUsize,
+    frames: AtomicUsize,
+    messages: AtomicUsize,
+    payload: AtomicUsize,
+    fragments: AtomicUsize,
+    fragmented: AtomicUsize,
+    reassembled: AtomicUsize,
+    keep_alives: AtomicUsize,
+}
+
+impl Counters {
+    pub fn add(&self, totals: &Totals) {
+        self.bytes.fetch_add(totals.bytes, Ordering::Relaxed);
+        self.frames.fetch_add(totals.frames, Ordering::Relaxed);
+        self.messages.fetch_add(totals.messages, Ordering::Relaxed);
+        self.payload.fetch_add(totals.payload, Ordering::Relaxed);
+        self.fragments
+            .fetch_add(totals.fragments, Ordering::Relaxed);
+        self.fragmented
+            .fetch_add(totals.fragmented, Ordering::Relaxed);
+        self.reassembled
+            .fetch_add(totals.reassembled, Ordering::Relaxed);
+        self.keep_alives
+            .fetch_add(totals.keep_alives, Ordering::Relaxed);
+    }
+
+    // Spawn the logging task, the throughput is the one of all the sessions
+    pub fn log(self: Arc<Self>, decode: bool) {
+        task::spawn(async move {
+            loop {
+                task::sleep(Duration::from_secs(1)).await;
+                let c = self.bytes.swap(0, Ordering::Relaxed);
+                let frames = self.frames.swap(0, Ordering::Relaxed);
+                let messages = self.messages.swap(0, Ordering::Relaxed);
+                let payload = self.payload.swap(0, Ordering::Relaxed);
+                let fragments = self.fragments.swap(0, Ordering::Relaxed);
+                let fragmented = self.fragmented.swap(0, Ordering::Relaxed);
+                let reassembled = self.reassembled.swap(0, Ordering::Relaxed);
+                let keep_alives = self.keep_alives.swap(0, Ordering::Relaxed);
+                if c > 0 {
+                    if decode {
+                        println!(
+                            "{:.6} Gbit/s, {} frames/s, {} msg/s, {:.6} Gbit/s payload, {} fragments/s, {:.2} fragments/msg, {} reassembly bytes/msg, {} keep-alives",
+                            (8_f64 * c as f64) / 1000000000_f64,
+                            frames,
+                            messages,
+                            (8_f64 * payload as f64) / 1000000000_f64,
+                            fragments,
+                            ratio(fragments, fragmented),
+                            ratio(reassembled, fragmented).floor() as usize,
+                            keep_alives
+                        );
+                    } else {
+                        println!("{:.6} Gbit/s", (8_f64 * c as f64) / 1000000000_f64);
+                    }
+                }
+            }
+        });
+    }
+}
+
+fn ratio(n: usize, d: usize) -> f64 {
+    if d == 0 {
+        return 0.0;
+    }
+    n as f64 / d as f64
+}
+
+// What has been received on a session. A fragmented message is accounted once
+// its final fragment is received: the fragments it was split into and the
+// bytes of the buffer needed to reassemble it.
+#[derive(Default)]
+pub struct Totals {
+    pub bytes: usize,
+    pub frames: usize,
+    pub messages: usize,
+    pub payload: usize,
+    pub fragments: usize,
+    pub fragmented: usize,
+    pub reassembled: usize,
+    pub keep_alives: usize,
+}
+
+impl Totals {
+    pub fn add(&mut self, other: &Totals) {
+        self.bytes += other.bytes;
+        self.frames += other.frames;
+        self.messages += other.messages;
+        self.payload += other.payload;
+        self.fragments += other.fragments;
+        self.fragmented += other.fragmented;
+        self.reassembled += other.reassembled;
+        self.keep_alives += other.keep_alives;
+    }
+
+    // Count a session message, the fragments of the message being reassembled
+    // are kept in the reassembly of the session until its final fragment
+    pub fn count(&mut self, message: &SessionMessage, reassembly: &mut Reassembly) {
+        match &message.body {
+            SessionBody::Frame(Frame { payload, .. }) => {
+                self.frames += 1;
+                match payload {
+                    FramePayload::Messages { messages } => {
+                        self.messages += messages.len();
+                        for m in messages.iter() {
+                            if let ZenohBody::Data(Data { payload, .. }) = &m.body {
+                                self.payload += payload.len();
+                            }
+                        }
+                    }
+                    FramePayload::Fragment { buffer, is_final } => {
+                        reassembly.fragments += 1;
+                        reassembly.bytes += buffer.len();
+                        if *is_final {
+                            self.messages += 1;
+                            self.fragments += reassembly.fragments;
+                            self.fragmented += 1;
+                            self.reassembled += reassembly.bytes;
+                            *reassembly = Reassembly::default();
+                        }
+                    }
+                }
+            }
+            SessionBody::KeepAlive(_) => self.keep_alives += 1,
+            _ => {}
+        }
+    }
+
+    pub fn print_summary<P: Display>(
+        &self,
+        peer: P,
+        reason: &str,
+        elapsed: Duration,
+        decode: bool,
+    ) {
+        let secs = elapsed.as_secs_f64();
+        let mut summary = format!(
+            "Session {} {} after {:.3} s: {} bytes, {:.6} Gbit/s",
+            peer,
+            reason,
+            secs,
+            self.bytes,
+            (8_f64 * self.bytes as f64) / 1000000000_f64 / secs
+        );
+        if decode {
+            summary.push_str(&format!(
+                ", {} frames, {} msgs, {} payload bytes, {} fragments, {:.2} fragments/msg, {} reassembly bytes/msg, {} keep-alives",
+                self.frames,
+                self.messages,
+                self.payload,
+                self.fragments,
+                ratio(self.fragments, self.fragmented),
+                ratio(self.reassembled, self.fragmented).floor() as usize,
+                self.keep_alives
+            ));
+        }
+        println!("{}", summary);
+    }
+}
+
+// The fragments received so far of the message being reassembled on a session
+#[derive(Default)]
+pub struct Reassembly {
+    fragments: usize,
+    bytes: usize,
+}
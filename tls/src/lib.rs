@@ -0,0 +1,192 @@
+//
+// Copyright (c) 2017, 2020 ADLINK Technology Inc.
+//
+// This program and the accompanying materials are made available under the
+// terms of the Eclipse Public License 2.0 which is available at
+// http://www.eclipse.org/legal/epl-2.0, or the Apache License, Version 2.0
+// which is available at https://www.apache.org/licenses/LICENSE-2.0.
+//
+// SPDX-License-Identifier: EPL-2.0 OR Apache-2.0
+//
+// Contributors:
+//   ADLINK zenoh team, <zenoh@adlink-labs.tech>
+//
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use structopt::StructOpt;
+use zenoh::net::protocol::session::SessionManagerOptionalConfig;
+use zenoh_util::properties::config::{
+    ConfigProperties, ZN_TLS_ROOT_CA_CERTIFICATE_KEY, ZN_TLS_SERVER_CERTIFICATE_KEY,
+    ZN_TLS_SERVER_PRIVATE_KEY_KEY,
+};
+use zenoh_util::properties::{IntKeyProperties, Properties};
+
+// The properties read by the TLS and QUIC links of zenoh
+pub const ROOT_CA_CERTIFICATE: &str = "tls_root_ca_certificate";
+pub const SERVER_PRIVATE_KEY: &str = "tls_server_private_key";
+pub const SERVER_CERTIFICATE: &str = "tls_server_certificate";
+
+// The certificates of the TLS and QUIC links, in PEM files
+pub struct Certificates {
+    pub root_ca: PathBuf,
+    pub certificate: PathBuf,
+    pub private_key: PathBuf,
+}
+
+impl Certificates {
+    fn new(dir: &Path) -> Self {
+        Self {
+            root_ca: dir.join("ca.pem"),
+            certificate: dir.join("cert.pem"),
+            private_key: dir.join("key.pem"),
+        }
+    }
+
+    fn exist(&self) -> bool {
+        self.root_ca.is_file() && self.certificate.is_file() && self.private_key.is_file()
+    }
+
+    // The properties to add to the configuration of a session, both the
+    // listening and the connecting sides are configured
+    pub fn properties(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (ROOT_CA_CERTIFICATE, self.root_ca.display().to_string()),
+            (SERVER_CERTIFICATE, self.certificate.display().to_string()),
+            (SERVER_PRIVATE_KEY, self.private_key.display().to_string()),
+        ]
+    }
+}
+
+// Generate a self-signed CA and the certificate it signs for the loopback
+// tests, the links have to be located on localhost
+fn generate(dir: &Path) -> Result<(), Box<dyn Error>> {
+    let mut params = CertificateParams::new(vec![]);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "zenoh-perf CA");
+    let ca = Certificate::from_params(params)?;
+
+    let mut params = CertificateParams::new(vec!["localhost".to_string()]);
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "localhost");
+    let certificate = Certificate::from_params(params)?;
+
+    let certificates = Certificates::new(dir);
+    fs::write(&certificates.root_ca, ca.serialize_pem()?)?;
+    fs::write(
+        &certificates.certificate,
+        certificate.serialize_pem_with_signer(&ca)?,
+    )?;
+    fs::write(
+        &certificates.private_key,
+        certificate.serialize_private_key_pem(),
+    )?;
+    Ok(())
+}
+
+// The certificates in the given directory, generated if missing. Both sides of
+// a test started at once share the same ones: they are generated aside and
+// moved in place, the first process to do so wins.
+pub fn certificates(dir: &Path) -> Result<Certificates, Box<dyn Error>> {
+    let certificates = Certificates::new(dir);
+    if certificates.exist() {
+        return Ok(certificates);
+    }
+
+    let tmp = dir.with_extension(format!("{}.tmp", process::id()));
+    fs::create_dir_all(&tmp)?;
+    generate(&tmp)?;
+    if let Err(e) = fs::rename(&tmp, dir) {
+        let _ = fs::remove_dir_all(&tmp);
+        if !certificates.exist() {
+            return Err(format!(
+                "Unable to store the certificates in {}: {}",
+                dir.display(),
+                e
+            )
+            .into());
+        }
+    }
+    Ok(certificates)
+}
+
+// The TLS option shared by the benchmarks, only available when they are built
+// with the transport_tls or transport_quic feature
+#[derive(Debug, StructOpt)]
+pub struct TlsOpt {
+    // The directory of the certificates of the TLS and QUIC links, a
+    // self-signed CA and a certificate for localhost are generated if missing
+    #[cfg(feature = "enabled")]
+    #[structopt(long = "tls-config", parse(from_os_str))]
+    tls_config: Option<PathBuf>,
+}
+
+impl TlsOpt {
+    // The certificates of --tls-config, if given
+    pub fn certificates(&self) -> Option<Certificates> {
+        #[cfg(feature = "enabled")]
+        if let Some(dir) = self.tls_config.as_ref() {
+            return Some(certificates(dir).unwrap());
+        }
+        None
+    }
+
+    // The properties of the certificates, empty without --tls-config
+    pub fn properties(&self) -> Properties {
+        let mut properties = Properties::default();
+        if let Some(certificates) = self.certificates() {
+            for (key, value) in certificates.properties() {
+                properties.insert(key.to_string(), value);
+            }
+        }
+        properties
+    }
+
+    // Add the certificates to the configuration of a zenoh-net or zenoh session
+    pub fn configure(&self, config: &mut Properties) {
+        for (key, value) in self.properties().iter() {
+            config.insert(key.clone(), value.clone());
+        }
+    }
+
+    // Add the certificates to the configuration of a runtime
+    pub fn configure_runtime(&self, config: &mut ConfigProperties) {
+        if let Some(certificates) = self.certificates() {
+            let path = |p: &Path| p.display().to_string();
+            config.insert(ZN_TLS_ROOT_CA_CERTIFICATE_KEY, path(&certificates.root_ca));
+            config.insert(
+                ZN_TLS_SERVER_CERTIFICATE_KEY,
+                path(&certificates.certificate),
+            );
+            config.insert(
+                ZN_TLS_SERVER_PRIVATE_KEY_KEY,
+                path(&certificates.private_key),
+            );
+        }
+    }
+
+    // The optional config of a session manager, the TLS and QUIC links read
+    // their certificates from it
+    pub async fn manager_config(&self) -> Option<SessionManagerOptionalConfig> {
+        self.manager_config_with(Properties::default()).await
+    }
+
+    // The same on top of the given properties, e.g. the ones of a --conf file
+    pub async fn manager_config_with(
+        &self,
+        mut properties: Properties,
+    ) -> Option<SessionManagerOptionalConfig> {
+        self.configure(&mut properties);
+        if properties.is_empty() {
+            return None;
+        }
+        SessionManagerOptionalConfig::from_properties(&IntKeyProperties::from(properties))
+            .await
+            .unwrap()
+    }
+}